@@ -0,0 +1,116 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use image::{
+    AnimationDecoder, DynamicImage, Frames,
+    codecs::{gif::GifDecoder, webp::WebPDecoder},
+};
+use ratatui::layout::Rect;
+use ratatui_image::{Resize, picker::Picker, protocol::Protocol};
+
+// huge animations are cut short instead of eating all memory
+const MAX_FRAMES: usize = 300;
+const MAX_BYTES: usize = 128 * 1024 * 1024;
+
+// browsers treat near-zero delays as 100ms, so do we
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+pub struct Animation {
+    frames: Vec<(Protocol, Duration)>,
+    current: usize,
+    shown_at: Instant,
+}
+
+impl Animation {
+    /// The frame to draw now, stepping past any whose delay has run out.
+    pub fn frame(&mut self) -> &Protocol {
+        let now = Instant::now();
+        let total: Duration = self.frames.iter().map(|(_, d)| *d).sum();
+
+        // after a long stall, pick up from here instead of replaying every step
+        if now.duration_since(self.shown_at) > total {
+            self.shown_at = now;
+        }
+
+        while now.duration_since(self.shown_at) >= self.frames[self.current].1 {
+            self.shown_at += self.frames[self.current].1;
+            self.current = (self.current + 1) % self.frames.len();
+        }
+
+        &self.frames[self.current].0
+    }
+
+    /// When the frame on screen runs out.
+    pub fn next_frame_at(&self) -> Instant {
+        self.shown_at + self.frames[self.current].1
+    }
+}
+
+/// Decode and encode every frame of an animated GIF or WebP, up to the caps.
+/// Returns `None` for stills, so they take the normal image path.
+pub fn load(
+    path: &Path,
+    picker: &Picker,
+    area: Rect,
+    max_w: u32,
+    max_h: u32,
+    cancelled: impl Fn() -> bool,
+) -> Option<Animation> {
+    let mut frames = Vec::new();
+    let mut bytes = 0;
+
+    for frame in decoder(path)?.take(MAX_FRAMES) {
+        if cancelled() {
+            return None;
+        }
+
+        // keep whatever decoded before a broken frame
+        let Ok(frame) = frame else {
+            break;
+        };
+
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = Duration::from_millis((numer / denom.max(1)) as u64);
+        let delay = if delay < MIN_DELAY { DEFAULT_DELAY } else { delay };
+
+        let img = DynamicImage::ImageRgba8(frame.into_buffer());
+        let img = if img.width() > max_w || img.height() > max_h {
+            img.thumbnail(max_w, max_h)
+        } else {
+            img
+        };
+
+        bytes += (img.width() * img.height() * 4) as usize;
+        if bytes > MAX_BYTES {
+            break;
+        }
+
+        let protocol = picker.new_protocol(img, area, Resize::Fit(None)).ok()?;
+        frames.push((protocol, delay));
+    }
+
+    (frames.len() > 1).then(|| Animation {
+        frames,
+        current: 0,
+        shown_at: Instant::now(),
+    })
+}
+
+fn decoder(path: &Path) -> Option<Frames<'static>> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let reader = BufReader::new(File::open(path).ok()?);
+
+    match ext.as_str() {
+        "gif" => Some(GifDecoder::new(reader).ok()?.into_frames()),
+        "webp" => {
+            let decoder = WebPDecoder::new(reader).ok()?;
+            decoder.has_animation().then(|| decoder.into_frames())
+        }
+        _ => None,
+    }
+}
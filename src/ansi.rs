@@ -0,0 +1,133 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+const TAB_WIDTH: usize = 4;
+
+/// Turn text with ANSI escape sequences into styled lines. Only SGR
+/// (colour and attribute) sequences are kept, everything else is dropped.
+pub fn to_lines(text: &str) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut style = Style::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                flush(&mut spans, &mut current, style);
+
+                match chars.next() {
+                    // CSI: parameters, then a final byte in @..~
+                    Some('[') => {
+                        let mut params = String::new();
+                        let mut last = None;
+
+                        for c in chars.by_ref() {
+                            if ('@'..='~').contains(&c) {
+                                last = Some(c);
+                                break;
+                            }
+                            params.push(c);
+                        }
+
+                        if last == Some('m') {
+                            style = apply_sgr(style, &params);
+                        }
+                    }
+                    // OSC: runs until BEL or ST
+                    Some(']') => {
+                        while let Some(c) = chars.next() {
+                            if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            '\n' => {
+                flush(&mut spans, &mut current, style);
+                lines.push(Line::from(std::mem::take(&mut spans)));
+            }
+            '\t' => current.push_str(&" ".repeat(TAB_WIDTH)),
+            '\r' => {}
+            c if c.is_control() => {}
+            c => current.push(c),
+        }
+    }
+
+    flush(&mut spans, &mut current, style);
+    if !spans.is_empty() {
+        lines.push(Line::from(spans));
+    }
+
+    lines
+}
+
+fn flush(spans: &mut Vec<Span<'static>>, current: &mut String, style: Style) {
+    if !current.is_empty() {
+        spans.push(Span::styled(std::mem::take(current), style));
+    }
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    // `ESC[m` is the same as `ESC[0m`
+    let codes: Vec<u16> = if params.is_empty() {
+        vec![0]
+    } else {
+        params.split([';', ':']).map(|p| p.parse().unwrap_or(0)).collect()
+    };
+
+    let mut codes = codes.into_iter();
+
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(Color::Indexed((code - 30) as u8)),
+            90..=97 => style.fg(Color::Indexed((code - 90 + 8) as u8)),
+            40..=47 => style.bg(Color::Indexed((code - 40) as u8)),
+            100..=107 => style.bg(Color::Indexed((code - 100 + 8) as u8)),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            39 => style.fg(Color::Reset),
+            49 => style.bg(Color::Reset),
+            _ => style,
+        };
+    }
+
+    style
+}
+
+// `5;n` for the 256 colour palette, `2;r;g;b` for truecolor
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()? as u8)),
+        2 => {
+            let r = codes.next()? as u8;
+            let g = codes.next()? as u8;
+            let b = codes.next()? as u8;
+            Some(Color::Rgb(r, g, b))
+        }
+        _ => None,
+    }
+}
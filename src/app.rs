@@ -4,18 +4,36 @@ use std::{
     thread,
     env,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use ratatui::layout::Rect;
 use ratatui_image::protocol::Protocol;
 use ratatui_image::picker::Picker;
 use std::sync::mpsc::{self, Sender};
-use std::num::NonZeroUsize;
-use lru::LruCache;
 use std::sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}};
-use image::GenericImageView;
+use image::{DynamicImage, GenericImageView, ImageDecoder, metadata::Orientation};
 use image::ImageReader;
 
+use crate::animation::{self, Animation};
+use crate::cache::{BackgroundCache, FileCache, FileStamp, PreviewCache};
+use crate::archive::{self, ArchiveEntry, ArchiveKind, ArchiveTask, ConflictPolicy};
+use crate::cli::{Args, ChooseMode};
+use crate::config::Config;
+use crate::data::DataPreviews;
+use crate::event::AppEvent;
+use crate::encoding::{self, Encoding, TextInfo};
+use crate::entry::{Entry, FileKind};
+use crate::exif::Exif;
+use crate::jobs::{Finished, Jobs};
+use crate::listing::{DirLoader, DirPreview, Loaded};
+use crate::markdown::MarkdownPreviews;
+use crate::media::{self, MediaInfo, MediaKind};
+use crate::notify::Notifications;
+use crate::pdf::PdfInfo;
+use crate::previewer::ExternalPreviews;
+use crate::textpreview::{TextPreviews, TextWindow};
+use crate::watch::Watcher;
+
 //
 // SORT MODE
 //
@@ -26,6 +44,17 @@ pub enum SortMode {
     Modified,
 }
 
+impl SortMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "name" => Some(Self::Name),
+            "size" => Some(Self::Size),
+            "modified" | "mtime" => Some(Self::Modified),
+            _ => None,
+        }
+    }
+}
+
 //
 // CLIPBOARD MODE
 //
@@ -33,6 +62,7 @@ pub enum SortMode {
 pub enum ClipboardMode {
     Copy,
     Cut,
+    Extract { archive: PathBuf, member: String },
 }
 
 #[derive(Clone, PartialEq)]
@@ -48,6 +78,9 @@ pub enum InputAction {
     CreateFolder,
     ConfirmDelete,
     OpenWith,
+    Extract,
+    Compress,
+    ResolveConflict,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -58,8 +91,10 @@ pub enum Focus {
 #[derive(Hash, Eq, PartialEq, Clone)]
 pub struct ImageKey {
     pub path: PathBuf,
+    pub stamp: FileStamp,
     pub width: u16,
     pub height: u16,
+    pub page: usize,
 }
 //problems with kitty dumb fonts
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -68,16 +103,80 @@ pub enum IconMode {
     Emoji,
     Nerd,
 }
+
+impl IconMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ascii" => Some(Self::Ascii),
+            "emoji" => Some(Self::Emoji),
+            "nerd" => Some(Self::Nerd),
+            _ => None,
+        }
+    }
+}
+//
+// ARCHIVE VIEW
+//
+// Read-only virtual directory inside an archive
+pub struct ArchiveView {
+    pub archive: PathBuf,
+    pub dir: String,
+    pub members: Vec<ArchiveEntry>,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl ArchiveView {
+    pub fn open(archive: PathBuf, members: Vec<ArchiveEntry>, show_hidden: bool) -> Self {
+        let entries = archive::children(&members, "", show_hidden);
+
+        Self {
+            archive,
+            dir: String::new(),
+            members,
+            entries,
+        }
+    }
+
+    pub fn cd(&mut self, dir: String, show_hidden: bool) {
+        self.entries = archive::children(&self.members, &dir, show_hidden);
+        self.dir = dir;
+    }
+
+    pub fn display_path(&self) -> PathBuf {
+        self.archive.join(&self.dir)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum PreviewKind {
+    Image,
+    Pdf,
+    Media(MediaKind),
+    Svg,
+    // image written by an external previewer
+    Rendered(PathBuf),
+}
+
 pub struct PreviewJob {
     pub request_id: u64,
+    pub key: ImageKey,
     pub path: PathBuf,
     pub inner: Rect,
-    pub is_pdf: bool,
+    pub kind: PreviewKind,
+    pub page: usize,
 }
 
 pub struct App {
     pub current_dir: PathBuf,
-    pub entries: Vec<fs::DirEntry>,
+    pub entries: Vec<Entry>,
+    // directory the entries were listed from
+    pub entries_dir: PathBuf,
+    pub loader: DirLoader,
+    // entries are still arriving from the loader, unsorted
+    pub listing_partial: bool,
+    // name of the entry to put the cursor on once the listing arrives
+    pub select_after_load: Option<String>,
+    pub watcher: Watcher,
     pub selected: usize,
     pub sort_mode: SortMode,
     pub clipboard: Option<(PathBuf, ClipboardMode)>,
@@ -93,41 +192,92 @@ pub struct App {
     pub image_id: u32,
     pub current_image: Option<std::path::PathBuf>,
     pub image: Option<Protocol>,
+    pub animation: Option<Animation>,
+    pub animation_rx: mpsc::Receiver<(u64, Animation)>,
     pub image_path: Option<std::path::PathBuf>,
+    pub image_stamp: Option<FileStamp>,
     pub picker: Picker,
     pub image_rx: Option<mpsc::Receiver<(u64, Option<Protocol>)>>,
     pub image_tx: mpsc::Sender<(u64, Option<Protocol>)>,
     pub image_loading: bool,
-    pub image_cache: Arc<Mutex<LruCache<ImageKey, Protocol>>>,
+    pub image_cache: Arc<Mutex<PreviewCache<ImageKey, Protocol>>>,
     pub preview_deadline: Option<std::time::Instant>,
     pub image_size: Option<(u16, u16)>,
     pub image_jobs: usize,
     pub image_request_id: u64,
     pub image_request_atomic: Arc<AtomicU64>,
     pub icon_mode: IconMode,
-    pub cursor_memory: HashMap<PathBuf, usize>,
+    // hovered entry name per directory
+    pub cursor_memory: HashMap<PathBuf, String>,
     pub preview_job_tx: Sender<PreviewJob>,
+    pub preview_scroll: usize,
+    pub preview_raw: bool,
+    pub preview_scroll_path: Option<PathBuf>,
+    pub preview_scroll_memory: HashMap<PathBuf, (usize, usize)>,
+    pub text_window: usize,
+    pub image_page: usize,
+    pub archive: Option<ArchiveView>,
+    pub archive_preview: BackgroundCache<Result<Vec<ArchiveEntry>, String>>,
+    // entered before its listing was in, opens once it is
+    pub entering_archive: Option<PathBuf>,
+    pub dir_size: BackgroundCache<u64>,
+    pub dir_preview: DirPreview,
+    pub pdf_info: BackgroundCache<Option<PdfInfo>>,
+    pub media_info: BackgroundCache<Option<MediaInfo>>,
+    pub exif: FileCache<Option<Exif>>,
+    pub dimensions: FileCache<Option<(u32, u32)>>,
+    pub exif_expanded: bool,
+    pub text_info: FileCache<Option<TextInfo>>,
+    pub data_previews: DataPreviews,
+    pub tree_depth: usize,
+    pub external_previews: ExternalPreviews,
+    pub text_previews: TextPreviews,
+    pub markdown_previews: MarkdownPreviews,
+    pub marked: HashSet<PathBuf>,
+    pub jobs: Jobs,
+    pub notices: Notifications,
+    pub show_log: bool,
+    // quit with the shell following to current_dir
+    pub cd_on_exit: bool,
+    // picking paths for a script instead of browsing
+    pub chooser: Option<ChooseMode>,
+    pub chosen: Option<Vec<PathBuf>>,
+    pub pending_task: Option<(ArchiveTask, Vec<String>)>,
+    // conflicts waiting for the prompt to be free
+    pub queued_conflicts: VecDeque<(ArchiveTask, Vec<String>)>,
 }
 
 impl App {
-    pub fn new() -> io::Result<Self> {
-        let current_dir = std::env::current_dir()?;
-        let show_hidden = false;
+    pub fn new(config: &Config, args: &Args, events: Sender<AppEvent>) -> io::Result<Self> {
+        // a file to start on opens its directory with it selected
+        let start = match &args.path {
+            Some(path) => fs::canonicalize(path)?,
+            None => std::env::current_dir()?,
+        };
+        let (current_dir, select_after_load) = match (start.is_file(), start.parent()) {
+            (true, Some(parent)) => (
+                parent.to_path_buf(),
+                start.file_name().map(|n| n.to_string_lossy().into_owned()),
+            ),
+            _ => (start.clone(), None),
+        };
+        let show_hidden = args.show_hidden;
 
         let (image_tx, image_rx) = mpsc::channel::<(u64, Option<Protocol>)>();
         let (job_tx, job_rx) = mpsc::channel::<PreviewJob>();
+        let (animation_tx, animation_rx) = mpsc::channel::<(u64, Animation)>();
 
 
         let cancel_token = Arc::new(AtomicU64::new(0));
         let worker_cancel = cancel_token.clone();
 
-        let entries = Self::read_dir(&current_dir, SortMode::Name, show_hidden)?;
-        let picker = Picker::from_query_stdio().unwrap();
-        let cache_size = NonZeroUsize::new(128).unwrap();
+        // terminals that don't answer the query still get block graphics
+        let picker = Picker::from_query_stdio().unwrap_or_else(|_| Picker::halfblocks());
         let picker_clone = picker.clone();
-        let cache_clone = Arc::new(Mutex::new(LruCache::new(cache_size)));
+        let cache_clone = Arc::new(Mutex::new(PreviewCache::new(config.preview_cache)));
         let worker_cache = cache_clone.clone();
         let result_tx = image_tx.clone();
+        let waker = events.clone();
 
         //worker thread
         thread::spawn(move || {
@@ -145,46 +295,108 @@ impl App {
                     continue;
                 }
 
+                let max_w = (job.inner.width as u32 * 8).min(2048).max(1);
+                let max_h = (job.inner.height as u32 * 16).min(2048).max(1);
+
+                //
+                // ANIMATION BRANCH
+                //
+                // frames aren't cached, the animation is dropped once the selection moves
+                if job.kind == PreviewKind::Image
+                    && let Some(animation) = animation::load(
+                        &job.path,
+                        &picker_clone,
+                        job.inner,
+                        max_w,
+                        max_h,
+                        || worker_cancel.load(Ordering::Relaxed) != request_id,
+                    )
+                {
+                    let _ = animation_tx.send((request_id, animation));
+                    let _ = waker.send(AppEvent::Worker);
+                    continue;
+                }
+
                 let result = (|| {
 
-                    let max_w = (job.inner.width as u32 * 8).min(2048).max(1);
-                    let max_h = (job.inner.height as u32 * 16).min(2048).max(1);
+                    let decoded = match &job.kind {
+                        //
+                        // PDF BRANCH
+                        //
+                        PreviewKind::Pdf => {
 
-                    //
-                    // PDF BRANCH
-                    //
-                    let decoded = if job.is_pdf {
+                            let scratch = ScratchDir::new(request_id).ok()?;
+                            let tmp_base = scratch.0.join("page");
 
-                        let tmp_base = format!("/tmp/fm_preview_{}", request_id);
+                            let page = (job.page + 1).to_string();
 
-                        let status = std::process::Command::new("pdftoppm")
-                            .arg("-png")
-                            .arg("-singlefile")
-                            .arg("-r")
-                            .arg("96")
-                            .arg(&job.path)
-                            .arg(&tmp_base)
-                            .status()
-                            .ok()?;
+                            let status = std::process::Command::new("pdftoppm")
+                                .arg("-png")
+                                .arg("-f")
+                                .arg(&page)
+                                .arg("-l")
+                                .arg(&page)
+                                .arg("-singlefile")
+                                .arg("-r")
+                                .arg("96")
+                                .arg(&job.path)
+                                .arg(&tmp_base)
+                                .status()
+                                .is_ok_and(|s| s.success());
 
-                        if !status.success() {
-                            return None;
-                        }
+                            let tmp_png = tmp_base.with_extension("png");
 
-                        let tmp_png = format!("{}.png", tmp_base);
+                            let img = status.then(|| image::open(&tmp_png).ok()).flatten();
 
-                        let img = image::open(&tmp_png).ok()?;
+                            let _ = std::fs::remove_file(&tmp_png);
 
-                        let _ = std::fs::remove_file(&tmp_png);
+                            img?
+                        }
 
-                        img
+                        //
+                        // MEDIA BRANCH
+                        //
+                        PreviewKind::Media(kind) => {
+                            // embedded cover art beats a decoded frame for audio
+                            let cover = if *kind == MediaKind::Audio {
+                                media::cover_art(&job.path)
+                            } else {
+                                None
+                            };
+
+                            let scratch = ScratchDir::new(request_id).ok()?;
+                            let tmp_png = scratch.0.join("frame.png");
+
+                            cover.or_else(|| media::thumbnail(&job.path, &tmp_png, max_w))?
+                        }
+
+                        //
+                        // SVG BRANCH
+                        //
+                        PreviewKind::Svg => {
+                            let scratch = ScratchDir::new(request_id).ok()?;
+                            let tmp_png = scratch.0.join("image.png");
+
+                            rasterize_svg(&job.path, &tmp_png, max_w, max_h)?
+                        }
 
-                    } else {
                         //
                         // Normal image branch
                         //
-                        let reader = ImageReader::open(&job.path).ok()?;
-                        reader.decode().ok()?
+                        PreviewKind::Image => {
+                            // turn photos upright according to their EXIF orientation
+                            let mut decoder = ImageReader::open(&job.path).ok()?.into_decoder().ok()?;
+                            let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+                            let mut img = DynamicImage::from_decoder(decoder).ok()?;
+                            img.apply_orientation(orientation);
+                            img
+                        }
+
+                        PreviewKind::Rendered(rendered) => {
+                            let reader = ImageReader::open(rendered).ok()?;
+                            reader.decode().ok()?
+                        }
                     };
                     let (w, h) = decoded.dimensions();
 
@@ -198,32 +410,30 @@ impl App {
                         decoded.thumbnail(max_w, max_h)
                     };
 
+                    // the encoded protocol is roughly as big as the pixels it came from
+                    let bytes = (resized.width() * resized.height() * 4) as usize;
+
                     let protocol = picker_clone
                         .new_protocol(resized, job.inner, ratatui_image::Resize::Fit(None))
                         .ok()?;
 
-                    Some(protocol)
+                    Some((protocol, bytes))
                 })();
-                if let Some(ref protocol) = result {
-                    worker_cache.lock().unwrap().put(
-                        ImageKey {
-                            path: job.path.clone(),
-                            width: quantize(job.inner.width),
-                            height: quantize(job.inner.height),
-                        },
-                        protocol.clone(),
-                    );
+                if let Some((protocol, bytes)) = &result {
+                    worker_cache.lock().unwrap().put(job.key.clone(), protocol.clone(), *bytes);
                 }
 
-                let _ = result_tx.send((request_id, result));
+                let _ = result_tx.send((request_id, result.map(|(protocol, _)| protocol)));
+                let _ = waker.send(AppEvent::Worker);
             }
         });
 
-        Ok(Self {
+        let mut app = Self {
+            entries_dir: PathBuf::new(),
             current_dir,
-            entries,
+            entries: Vec::new(),
             selected: 0,
-            sort_mode: SortMode::Name,
+            sort_mode: args.sort.unwrap_or(SortMode::Name),
             clipboard: None,
             mode: AppMode::Normal,
             input: String::new(),
@@ -238,7 +448,10 @@ impl App {
             current_image: None,
             picker,
             image: None,
+            animation: None,
+            animation_rx,
             image_path: None,
+            image_stamp: None,
             image_tx,
             image_rx: Some(image_rx),
             image_loading: false,
@@ -248,11 +461,55 @@ impl App {
             image_jobs: 0,
             image_request_id: 0,
             image_request_atomic: cancel_token,
-            icon_mode: detect_icon_mode(),
+            icon_mode: args.icons.unwrap_or_else(detect_icon_mode),
             cursor_memory: HashMap::new(),
             preview_job_tx: job_tx,
-        })
+            preview_scroll: 0,
+            preview_raw: false,
+            preview_scroll_path: None,
+            preview_scroll_memory: HashMap::new(),
+            text_window: 0,
+            image_page: 0,
+            archive: None,
+            archive_preview: BackgroundCache::new(events.clone()),
+            entering_archive: None,
+            dir_size: BackgroundCache::new(events.clone()),
+            dir_preview: DirPreview::new(events.clone()),
+            pdf_info: BackgroundCache::new(events.clone()),
+            media_info: BackgroundCache::new(events.clone()),
+            exif: FileCache::new(),
+            dimensions: FileCache::new(),
+            exif_expanded: false,
+            text_info: FileCache::new(),
+            data_previews: DataPreviews::new(events.clone()),
+            tree_depth: 2,
+            external_previews: ExternalPreviews::new(events.clone()),
+            text_previews: TextPreviews::new(events.clone()),
+            markdown_previews: MarkdownPreviews::new(events.clone()),
+            marked: HashSet::new(),
+            loader: DirLoader::new(events.clone()),
+            listing_partial: false,
+            select_after_load,
+            watcher: Watcher::new(events.clone()),
+            jobs: Jobs::new(events),
+            notices: Notifications::new(),
+            show_log: false,
+            cd_on_exit: false,
+            chooser: args.choose.as_ref().map(|c| c.mode),
+            chosen: None,
+            pending_task: None,
+            queued_conflicts: VecDeque::new(),
+        };
+
+        app.refresh()?;
+        Ok(app)
     }
+    fn report_pinned(&mut self) {
+        if let Err(e) = self.save_pinned() {
+            self.notices.warn(format!("Couldn't save pinned folders: {}", e));
+        }
+    }
+
     //save pin dir
     pub fn save_pinned(&self) -> io::Result<()> {
         let path = dirs::config_dir()
@@ -298,78 +555,270 @@ impl App {
         Ok(())
     }
 
-    fn read_dir(
-        path: &PathBuf,
-        mode: SortMode,
-        show_hidden: bool,
-    ) -> io::Result<Vec<fs::DirEntry>> {
-        use std::cmp::Ordering;
-        use std::fs;
-
-        let mut entries: Vec<_> = fs::read_dir(path)?
-            .filter_map(Result::ok)
-            .filter(|e| {
-                if let Some(name) = e.file_name().to_str() {
-                    if !show_hidden && name.starts_with('.') {
-                        return false;
+    pub fn refresh(&mut self) -> io::Result<()> {
+        // a new directory fills in as it loads, reloading the
+        // current one keeps the old listing until the new one is ready
+        if self.entries_dir != self.current_dir {
+            self.entries.clear();
+            self.entries_dir = self.current_dir.clone();
+            self.listing_partial = true;
+            if self.archive.is_none() {
+                self.selected = 0;
+            }
+        }
+
+        // reload entries first
+        self.loader.load(&self.current_dir, self.sort_mode, self.show_hidden);
+
+        if let Some(view) = &mut self.archive {
+            view.cd(view.dir.clone(), self.show_hidden);
+            self.selected = self.selected.min(view.entries.len().saturating_sub(1));
+        }
+
+        Ok(())
+    }
+
+    // reload for changes made outside fren and keep watching
+    // the current directory and the hovered one
+    pub fn poll_watch(&mut self) {
+        let changed = self.watcher.poll();
+
+        for path in &changed {
+            self.invalidate_preview(path);
+            if let Some(parent) = path.parent() {
+                // a directory's size and listing cover its files
+                self.dir_size.invalidate(parent);
+                self.dir_preview.invalidate(parent);
+            }
+        }
+
+        let here = changed
+            .iter()
+            .any(|p| *p == self.current_dir || p.parent() == Some(self.current_dir.as_path()));
+        if here {
+            let _ = self.refresh();
+        }
+
+        let hovered = self
+            .entries
+            .get(self.selected)
+            .filter(|e| e.is_dir && self.archive.is_none())
+            .map(|e| e.path.clone());
+
+        let mut dirs = vec![self.current_dir.as_path()];
+        dirs.extend(hovered.as_deref());
+        self.watcher.watch(&dirs);
+    }
+
+    // take in what the directory loader produced since the last frame
+    pub fn poll_listing(&mut self) {
+        for loaded in self.loader.poll() {
+            match loaded {
+                Loaded::Chunk(chunk) => {
+                    if self.listing_partial {
+                        self.entries.extend(chunk);
                     }
                 }
-                true
-            })
-            .collect();
+                Loaded::Done(Ok(entries)) => {
+                    // keep the hovered entry under the cursor through the sort,
+                    // unless the cursor hasn't moved in a listing that is still filling in
+                    let moved = !self.listing_partial || self.selected > 0;
+                    let target = self
+                        .select_after_load
+                        .take()
+                        .or_else(|| self.hovered_name().filter(|_| moved))
+                        .or_else(|| self.cursor_memory.get(&self.current_dir).cloned());
+
+                    self.entries = entries;
+                    self.listing_partial = false;
+
+                    if self.archive.is_some() {
+                        continue;
+                    }
 
-        //
-        // PRIMARY SORT
-        //
-        match mode {
-            SortMode::Name => {
-                entries.sort_by(|a, b| {
-                    let a_name = a.file_name().to_string_lossy().to_string();
-                    let b_name = b.file_name().to_string_lossy().to_string();
-                    natord::compare_ignore_case(&a_name, &b_name)
-                });
-            }
-            SortMode::Size => {
-                entries.sort_by_key(|e| e.metadata().map(|m| m.len()).unwrap_or(0));
-            }
-            SortMode::Modified => {
-                entries.sort_by_key(|e| e.metadata().and_then(|m| m.modified()).ok());
+                    // by name, so the cursor follows its file; when that file is gone
+                    // the cursor stays where it was
+                    if let Some(pos) = target.and_then(|name| self.entries.iter().position(|e| e.name == name)) {
+                        self.selected = pos;
+                    }
+                    self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+                }
+                Loaded::Done(Err(e)) => {
+                    self.listing_partial = false;
+                    self.notices.error(format!("Can't list {}: {}", self.current_dir.display(), e));
+                }
             }
         }
 
-        //
-        // SECONDARY SORT: directories first (stable)
-        //
-        entries.sort_by(|a, b| {
-            let a_dir = a.file_type().map(|t| t.is_dir()).unwrap_or(false);
-            let b_dir = b.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if let Some(path) = self.entering_archive.take()
+            && self.archive.is_none()
+            && self.entries.get(self.selected).is_some_and(|e| e.path == path)
+            && let Err(e) = self.open_archive(path)
+        {
+            self.notices.error(e.to_string());
+        }
+    }
+
+    // swap in the remembered scroll position when the previewed file changes
+    pub fn sync_preview_scroll(&mut self, path: &Path) {
+        if self.preview_scroll_path.as_deref() == Some(path) {
+            return;
+        }
 
-            if a_dir != b_dir {
-                return if a_dir {
-                    Ordering::Less
-                } else {
-                    Ordering::Greater
-                };
+        if let Some(old) = self.preview_scroll_path.take() {
+            if self.preview_scroll > 0 || self.text_window > 0 {
+                self.preview_scroll_memory.insert(old, (self.text_window, self.preview_scroll));
+            } else {
+                self.preview_scroll_memory.remove(&old);
             }
+        }
 
-            Ordering::Equal // keep previous ordering within groups
-        });
+        (self.text_window, self.preview_scroll) = self.preview_scroll_memory.get(path).copied().unwrap_or((0, 0));
+        self.preview_scroll_path = Some(path.to_path_buf());
+    }
+
+    pub fn scroll_preview(&mut self, delta: isize) {
+        // above the top of a later text window, the one before takes over
+        if delta < 0
+            && self.preview_scroll < delta.unsigned_abs()
+            && let Some(path) = &self.preview_scroll_path
+            && self.text_previews.scroll_up(path, &mut self.text_window, &mut self.preview_scroll, delta.unsigned_abs())
+        {
+            return;
+        }
 
-        Ok(entries)
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(delta);
     }
 
-    pub fn refresh(&mut self) -> io::Result<()> {
-        // reload entries first
-        self.entries = Self::read_dir(&self.current_dir, self.sort_mode, self.show_hidden)?;
+    // step through PDF pages; other previews ignore the page keys
+    pub fn change_page(&mut self, delta: isize) {
+        if self.preview_scroll_path.as_deref().is_some_and(crate::pdf::is_pdf) {
+            self.scroll_preview(delta);
+        }
+    }
 
-        // restore cursor if we have memory
-        if let Some(&pos) = self.cursor_memory.get(&self.current_dir) {
-            self.selected = pos.min(self.entries.len().saturating_sub(1));
-        } else {
-            self.selected = 0;
+    // total size of the files directly inside a directory, summed once per change
+    // in the background; `None` until it's in
+    pub fn dir_size_for(&mut self, path: &Path) -> Option<u64> {
+        self.dir_size
+            .request(path, |path| {
+                fs::read_dir(path)
+                    .map(|entries| {
+                        entries
+                            .flatten()
+                            .filter_map(|e| e.metadata().ok())
+                            .filter(|m| m.is_file())
+                            .map(|m| m.len())
+                            .sum()
+                    })
+                    .unwrap_or(0)
+            })
+            .copied()
+    }
+
+    // members of the hovered archive, listed once per file in the background;
+    // `None` until they're in, which for a big compressed archive takes a while
+    pub fn archive_listing_for(&mut self, path: &Path) -> Option<&Result<Vec<ArchiveEntry>, String>> {
+        self.archive_preview.request(path, list_archive)
+    }
+
+    // pdfinfo output for the hovered PDF, fetched once per file in the background;
+    // `None` until it's in, `Some(None)` without pdfinfo
+    pub fn pdf_info_for(&mut self, path: &Path) -> Option<Option<PdfInfo>> {
+        self.pdf_info.request(path, crate::pdf::info).cloned()
+    }
+
+    // ffprobe output (or audio tags) for the hovered file, fetched once per file
+    // in the background; `None` until it's in, `Some(None)` when there's nothing
+    pub fn media_info_for(&mut self, path: &Path) -> Option<Option<MediaInfo>> {
+        self.media_info.request(path, media::info).cloned()
+    }
+
+    // EXIF/XMP for the hovered image, parsed once per file
+    pub fn exif_for(&mut self, path: &Path) -> Option<Exif> {
+        self.exif.get_or_insert_with(path, || crate::exif::read(path)).clone()
+    }
+
+    // width and height from the image header, read once per file
+    pub fn dimensions_for(&mut self, path: &Path) -> Option<(u32, u32)> {
+        *self.dimensions.get_or_insert_with(path, || get_dimensions(path))
+    }
+
+    // encoding and line endings, sniffed once per file; None for binary files
+    pub fn text_info_for(&mut self, path: &Path) -> Option<TextInfo> {
+        *self.text_info.get_or_insert_with(path, || encoding::sniff(path))
+    }
+
+    // The window of a text file that holds the scroll position; `None` while loading.
+    pub fn text_preview_for(
+        &mut self,
+        path: &Path,
+        encoding: Encoding,
+        visible: usize,
+    ) -> Option<Result<Arc<TextWindow>, String>> {
+        self.text_previews
+            .view(path, encoding, &mut self.text_window, &mut self.preview_scroll, visible)
+    }
+
+    /// Drop everything cached for `path`, for when it changes or goes away.
+    pub fn invalidate_preview(&mut self, path: &Path) {
+        self.image_cache.lock().unwrap().remove_where(|key| key.path == path);
+        self.text_previews.invalidate(path);
+        self.markdown_previews.invalidate(path);
+        self.external_previews.invalidate(path);
+
+        self.archive_preview.invalidate(path);
+        self.dir_size.invalidate(path);
+        self.dir_preview.invalidate(path);
+        self.pdf_info.invalidate(path);
+        self.media_info.invalidate(path);
+        self.exif.invalidate(path);
+        self.dimensions.invalidate(path);
+        self.text_info.invalidate(path);
+        self.data_previews.invalidate(path);
+
+        // the image on screen is reloaded on the next draw
+        if self.image_path.as_deref() == Some(path) {
+            self.image_path = None;
         }
+    }
 
-        Ok(())
+    // when the screen changes without any event: the next animation frame,
+    // the end of the preview debounce or a toast timing out
+    pub fn next_wakeup(&self) -> Option<std::time::Instant> {
+        let now = std::time::Instant::now();
+        let frame = self.animation.as_ref().map(Animation::next_frame_at);
+
+        [
+            frame,
+            self.preview_deadline,
+            self.loader.indicator_at(),
+            self.dir_preview.indicator_at(),
+            self.notices.toast_expires_at(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&at| at > now)
+        .min()
+    }
+
+    /// Run a file operation, reporting a failure instead of passing it up.
+    pub fn attempt(&mut self, what: &str, op: impl FnOnce(&mut Self) -> io::Result<()>) {
+        if let Err(e) = op(self) {
+            self.notices.error(format!("{} failed: {}", what, e));
+        }
+    }
+
+    // half of the visible preview lines
+    pub fn preview_half_page(&self) -> isize {
+        (self.preview_rect.height.saturating_sub(2) / 2).max(1) as isize
+    }
+
+    pub fn entry_count(&self) -> usize {
+        match &self.archive {
+            Some(view) => view.entries.len(),
+            None => self.entries.len(),
+        }
     }
 
     pub fn toggle_hidden(&mut self) -> io::Result<()> {
@@ -383,22 +832,197 @@ impl App {
             SortMode::Size => SortMode::Modified,
             SortMode::Modified => SortMode::Name,
         };
+        self.select_after_load = self.hovered_name();
         self.refresh()
     }
 
+    pub fn toggle_mark(&mut self) {
+        if let Some(entry) = self.entries.get(self.selected) {
+            let path = entry.path.clone();
+            if !self.marked.remove(&path) {
+                self.marked.insert(path);
+            }
+
+            if self.selected + 1 < self.entries.len() {
+                self.selected += 1;
+            }
+        }
+    }
+
+    // marks are by path, so they follow `from` (and whatever is under it) to `to`,
+    // or go away with it when there's nowhere to follow
+    fn move_marks(&mut self, from: &Path, to: Option<&Path>) {
+        let moved: Vec<PathBuf> = self.marked.iter().filter(|p| p.starts_with(from)).cloned().collect();
+
+        for path in moved {
+            self.marked.remove(&path);
+            if let Some(to) = to
+                && let Ok(rest) = path.strip_prefix(from)
+            {
+                self.marked.insert(to.join(rest));
+            }
+        }
+    }
+
+    pub fn hovered_name(&self) -> Option<String> {
+        self.entries.get(self.selected).map(|e| e.name.clone())
+    }
+
+    // marked paths, or the hovered entry when nothing is marked
+    pub fn selection(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() {
+            return self.entries.get(self.selected).map(|e| e.path.clone()).into_iter().collect();
+        }
+
+        let mut paths: Vec<PathBuf> = self.marked.iter().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// In chooser mode, take the marked paths or the hovered file, or for a
+    /// directory the marked ones or the one being browsed. True once chosen.
+    pub fn choose(&mut self) -> bool {
+        let Some(mode) = self.chooser else {
+            return false;
+        };
+
+        // archive members have no path to hand back
+        if self.archive.is_some() {
+            return false;
+        }
+
+        let paths = match mode {
+            ChooseMode::Files => self.selection(),
+            ChooseMode::Dir if self.marked.is_empty() => vec![self.current_dir.clone()],
+            ChooseMode::Dir => self.selection().into_iter().filter(|p| p.is_dir()).collect(),
+        };
+
+        if paths.is_empty() {
+            return false;
+        }
+
+        self.chosen = Some(paths);
+        true
+    }
+
+    pub fn hovered_archive(&self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?;
+        (entry.is_file && ArchiveKind::detect(&entry.path).is_some()).then(|| entry.path.clone())
+    }
+
+    pub fn extract_hovered(&mut self, folder: &str) {
+        if let Some(archive) = self.hovered_archive() {
+            let folder = folder.trim();
+
+            self.jobs.spawn(
+                ArchiveTask::Extract {
+                    archive,
+                    dest: self.current_dir.clone(),
+                    subfolder: (!folder.is_empty()).then(|| folder.to_string()),
+                    member: None,
+                },
+                None,
+            );
+        }
+    }
+
+    pub fn compress_selection(&mut self, name: &str) {
+        let sources = self.selection();
+
+        if sources.is_empty() {
+            return;
+        }
+
+        if ArchiveKind::detect(Path::new(name)).is_none() {
+            self.notices.warn("Unknown archive format, use .zip, .tar, .tar.gz, .tar.xz, .tar.zst or .gz");
+            return;
+        }
+
+        self.jobs.spawn(
+            ArchiveTask::Create {
+                target: self.current_dir.join(name),
+                sources,
+            },
+            None,
+        );
+        self.marked.clear();
+    }
+
+    pub fn resolve_conflict(&mut self, policy: Option<ConflictPolicy>) {
+        if let (Some((task, _)), Some(policy)) = (self.pending_task.take(), policy) {
+            self.jobs.spawn(task, Some(policy));
+        }
+        self.mode = AppMode::Normal;
+    }
+
+    pub fn poll_jobs(&mut self) -> io::Result<()> {
+        for finished in self.jobs.poll() {
+            match finished {
+                Finished::Done(message) => {
+                    self.notices.info(message);
+                    if self.archive.is_none() {
+                        self.refresh()?;
+                    }
+                }
+                Finished::Failed(message) => {
+                    self.notices.error(message);
+                    if self.archive.is_none() {
+                        self.refresh()?;
+                    }
+                }
+                Finished::Conflict(task, names) => {
+                    self.queued_conflicts.push_back((task, names));
+                }
+            }
+        }
+
+        // a prompt being typed into is left alone, the question waits its turn
+        if self.mode == AppMode::Normal
+            && self.pending_task.is_none()
+            && let Some(conflict) = self.queued_conflicts.pop_front()
+        {
+            self.pending_task = Some(conflict);
+            self.mode = AppMode::Input(InputAction::ResolveConflict);
+        }
+
+        Ok(())
+    }
+
     pub fn copy_selected(&mut self) {
+        if let Some(view) = &self.archive {
+            if let Some(entry) = view.entries.get(self.selected) {
+                self.clipboard = Some((
+                    view.archive.join(&entry.path),
+                    ClipboardMode::Extract {
+                        archive: view.archive.clone(),
+                        member: entry.path.clone(),
+                    },
+                ));
+            }
+            return;
+        }
+
         if let Some(entry) = self.entries.get(self.selected) {
-            self.clipboard = Some((entry.path(), ClipboardMode::Copy));
+            self.clipboard = Some((entry.path.clone(), ClipboardMode::Copy));
         }
     }
 
     pub fn cut_selected(&mut self) {
+        // archives are read-only
+        if self.archive.is_some() {
+            return;
+        }
+
         if let Some(entry) = self.entries.get(self.selected) {
-            self.clipboard = Some((entry.path(), ClipboardMode::Cut));
+            self.clipboard = Some((entry.path.clone(), ClipboardMode::Cut));
         }
     }
 
     pub fn paste(&mut self) -> io::Result<()> {
+        if self.archive.is_some() {
+            return Ok(());
+        }
+
         if let Some((source, mode)) = self.clipboard.clone() {
             let file_name = match source.file_name() {
                 Some(name) => name,
@@ -411,12 +1035,29 @@ impl App {
                 return Ok(());
             }
 
+            self.select_after_load = Some(file_name.to_string_lossy().into_owned());
+
             match mode {
                 ClipboardMode::Copy => Self::copy_recursively(&source, &destination)?,
                 ClipboardMode::Cut => {
                     fs::rename(&source, &destination)?;
+                    self.invalidate_preview(&source);
+                    self.move_marks(&source, Some(&destination));
                     self.clipboard = None;
                 }
+                // big members take a while, the listing refreshes when the job ends
+                ClipboardMode::Extract { archive, member } => {
+                    self.jobs.spawn(
+                        ArchiveTask::Extract {
+                            archive,
+                            dest: self.current_dir.clone(),
+                            subfolder: None,
+                            member: Some(member),
+                        },
+                        None,
+                    );
+                    return Ok(());
+                }
             }
 
             self.refresh()?;
@@ -449,12 +1090,14 @@ impl App {
 
     pub fn trash_selected(&mut self) -> io::Result<()> {
         if let Some(entry) = self.entries.get(self.selected) {
-            let source = entry.path();
+            let source = entry.path.clone();
             let trash_dir = Self::trash_path();
 
             fs::create_dir_all(&trash_dir)?;
 
-            let file_name = source.file_name().unwrap();
+            let file_name = source
+                .file_name()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "nothing to trash"))?;
             let mut target = trash_dir.join(file_name);
 
             // Avoid overwrite if same name exists
@@ -469,19 +1112,37 @@ impl App {
                 counter += 1;
             }
 
-            fs::rename(source, target)?;
+            fs::rename(&source, target)?;
+            self.invalidate_preview(&source);
+            self.move_marks(&source, None);
         }
 
         self.refresh()
     }
 
     pub fn enter(&mut self) -> io::Result<()> {
+        if let Some(view) = &mut self.archive {
+            let dir = view
+                .entries
+                .get(self.selected)
+                .filter(|e| e.is_dir)
+                .map(|e| e.path.clone());
+
+            if let Some(dir) = dir {
+                view.cd(dir, self.show_hidden);
+                self.selected = 0;
+            }
+            return Ok(());
+        }
+
         if let Some(entry) = self.entries.get(self.selected) {
-            let path = entry.path();
+            let path = entry.path.clone();
 
-            if path.is_dir() {
+            if entry.is_dir {
                 self.current_dir = path;
                 self.refresh()?;
+            } else if path.is_file() && ArchiveKind::detect(&path).is_some() {
+                self.open_archive(path)?;
             } else if path.is_file() {
                 self.open_with_program("xdg-open")?;
             }
@@ -489,8 +1150,48 @@ impl App {
         Ok(())
     }
 
+    // browse into an archive once it's listed; until then it opens when the listing
+    // comes in, if it's still hovered
+    fn open_archive(&mut self, path: PathBuf) -> io::Result<()> {
+        let Some(listing) = self.archive_listing_for(&path).cloned() else {
+            self.entering_archive = Some(path);
+            return Ok(());
+        };
+
+        self.entering_archive = None;
+
+        match listing {
+            Ok(members) => {
+                self.archive = Some(ArchiveView::open(path, members, self.show_hidden));
+                self.selected = 0;
+            }
+            // not readable as an archive, hand it to the desktop
+            Err(_) => self.open_with_program("xdg-open")?,
+        }
+
+        Ok(())
+    }
+
     pub fn up(&mut self) -> io::Result<()> {
+        if let Some(view) = &mut self.archive {
+            if view.dir.is_empty() {
+                // leave the archive with it selected
+                let archive = view.archive.clone();
+                self.archive = None;
+                self.select_after_load = archive.file_name().map(|n| n.to_string_lossy().into_owned());
+                self.refresh()?;
+            } else {
+                let left = view.dir.clone();
+                let parent = left.rsplit_once('/').map(|(p, _)| p.to_string()).unwrap_or_default();
+                view.cd(parent, self.show_hidden);
+                self.selected = view.entries.iter().position(|e| e.path == left).unwrap_or(0);
+            }
+            return Ok(());
+        }
+
         if let Some(parent) = self.current_dir.parent() {
+            // land on the directory we just left
+            self.select_after_load = self.current_dir.file_name().map(|n| n.to_string_lossy().into_owned());
             self.current_dir = parent.to_path_buf();
             self.refresh()?;
         }
@@ -499,7 +1200,7 @@ impl App {
 
     pub fn open_with_program(&self, program: &str) -> io::Result<()> {
         if let Some(entry) = self.entries.get(self.selected) {
-            Command::new(program).arg(entry.path()).spawn()?;
+            Command::new(program).arg(&entry.path).spawn()?;
         }
         Ok(())
     }
@@ -509,6 +1210,7 @@ impl App {
         if !new_path.exists() {
             fs::create_dir(&new_path)?;
         }
+        self.select_after_load = Some(name.to_string());
         self.refresh()
     }
 
@@ -517,6 +1219,7 @@ impl App {
         if !new_path.exists() {
             File::create(&new_path)?;
         }
+        self.select_after_load = Some(name.to_string());
         self.refresh()
     }
 
@@ -527,9 +1230,12 @@ impl App {
 
     pub fn confirm_rename(&mut self) -> io::Result<()> {
         if let Some(entry) = self.entries.get(self.selected) {
-            let old_path = entry.path();
+            let old_path = entry.path.clone();
             let new_path = self.current_dir.join(&self.input);
-            fs::rename(old_path, new_path)?;
+            fs::rename(&old_path, &new_path)?;
+            self.invalidate_preview(&old_path);
+            self.move_marks(&old_path, Some(&new_path));
+            self.select_after_load = Some(self.input.clone());
         }
 
         self.mode = AppMode::Normal;
@@ -539,6 +1245,7 @@ impl App {
 
     pub fn open_pinned(&mut self) -> io::Result<()> {
         if let Some(path) = self.pinned.get(self.pinned_selected) {
+            self.archive = None;
             self.current_dir = path.clone();
             self.refresh()?;
         }
@@ -547,10 +1254,10 @@ impl App {
 
     pub fn pin_selected(&mut self) {
         if let Some(entry) = self.entries.get(self.selected) {
-            let path = entry.path();
-            if path.is_dir() && !self.pinned.contains(&path) {
+            let path = entry.path.clone();
+            if entry.is_dir && !self.pinned.contains(&path) {
                 self.pinned.push(path);
-                let _ = self.save_pinned();
+                self.report_pinned();
             }
         }
     }
@@ -558,71 +1265,66 @@ impl App {
     pub fn unpin_selected(&mut self) {
         if self.pinned_selected < self.pinned.len() {
             self.pinned.remove(self.pinned_selected);
-            let _ = self.save_pinned();
+            self.report_pinned();
             if self.pinned_selected > 0 {
                 self.pinned_selected -= 1;
             }
         }
     }
-    pub fn icon_for(path: &std::path::Path, mode: IconMode) -> &'static str {
+    // for paths that don't exist on disk, e.g. archive members
+    pub fn icon_for_kind(path: &Path, is_dir: bool, mode: IconMode) -> &'static str {
+        Self::icon(FileKind::detect(path, is_dir), mode)
+    }
+    pub fn icon(kind: FileKind, mode: IconMode) -> &'static str {
         match mode {
-            IconMode::Ascii => Self::ascii_icon(path),
-            IconMode::Emoji => Self::emoji_icon(path),
-            IconMode::Nerd => Self::nerd_icon(path),
+            IconMode::Ascii => Self::ascii_icon(kind),
+            IconMode::Emoji => Self::emoji_icon(kind),
+            IconMode::Nerd => Self::nerd_icon(kind),
         }
     }
-    pub fn emoji_icon(path: &Path) -> &'static str {
-        if path.is_dir() {
-            return "📁 ";
-        }
-
-        match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
-            "png" | "jpg" | "jpeg" | "webp" | "gif" => "🖼  ",
-            "mp3" | "wav" | "flac" => "🎵 ",
-            "mp4" | "mkv" | "mov" => "🎬 ",
-            "zip" | "tar" | "gz" | "rar" => "📦 ",
-            "rs" => "🦀 ",
-            "c" | "cpp" | "h" => "💻 ",
-            "py" => "🐍 ",
-            "js" | "ts" => "📜 ",
-            "toml" | "json" | "yaml" | "yml" => "⚙  ",
-            _ => "📄 ",
+    pub fn emoji_icon(kind: FileKind) -> &'static str {
+        match kind {
+            FileKind::Directory => "📁 ",
+            FileKind::Image => "🖼  ",
+            FileKind::Audio => "🎵 ",
+            FileKind::Video => "🎬 ",
+            FileKind::Archive => "📦 ",
+            FileKind::Rust => "🦀 ",
+            FileKind::C => "💻 ",
+            FileKind::Python => "🐍 ",
+            FileKind::JavaScript | FileKind::TypeScript => "📜 ",
+            FileKind::Config => "⚙  ",
+            FileKind::Other => "📄 ",
         }
     }
 
-    pub fn ascii_icon(path: &Path) -> &'static str {
-        if path.is_dir() {
-            return "[D] ";
-        }
-
-        match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
-            "png" | "jpg" | "jpeg" | "webp" | "gif" => "[I] ",
-            "mp3" | "wav" | "flac" => "[A] ",
-            "mp4" | "mkv" | "mov" => "[V] ",
-            "zip" | "tar" | "gz" | "rar" => "[Z] ",
-            "rs" | "c" | "cpp" | "h" | "py" | "js" | "ts" => "[S] ",
-            "toml" | "json" | "yaml" | "yml" => "[C] ",
-            _ => "[F] ",
+    pub fn ascii_icon(kind: FileKind) -> &'static str {
+        match kind {
+            FileKind::Directory => "[D] ",
+            FileKind::Image => "[I] ",
+            FileKind::Audio => "[A] ",
+            FileKind::Video => "[V] ",
+            FileKind::Archive => "[Z] ",
+            FileKind::Rust | FileKind::C | FileKind::Python | FileKind::JavaScript | FileKind::TypeScript => "[S] ",
+            FileKind::Config => "[C] ",
+            FileKind::Other => "[F] ",
         }
     }
 
-    pub fn nerd_icon(path: &Path) -> &'static str {
-        if path.is_dir() {
-            return "󰉋 "; // nf-md-folder
-        }
-
-        match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
-            "png" | "jpg" | "jpeg" | "webp" | "gif" => "󰋩 ", // nf-md-image
-            "mp3" | "wav" | "flac" => "󰎈 ", // nf-md-music
-            "mp4" | "mkv" | "mov" => "󰕧 ", // nf-md-video
-            "zip" | "tar" | "gz" | "rar" => "󰀼 ", // nf-md-archive
-            "rs" => " ", // nf-dev-rust
-            "c" | "cpp" | "h" => " ", // nf-dev-c
-            "py" => " ", // nf-dev-python
-            "js" => " ", // nf-dev-javascript
-            "ts" => " ", // nf-dev-typescript
-            "toml" | "json" | "yaml" | "yml" => " ", // nf-seti-config
-            _ => "󰈔 ", // nf-md-file
+    pub fn nerd_icon(kind: FileKind) -> &'static str {
+        match kind {
+            FileKind::Directory => "󰉋 ", // nf-md-folder
+            FileKind::Image => "󰋩 ", // nf-md-image
+            FileKind::Audio => "󰎈 ", // nf-md-music
+            FileKind::Video => "󰕧 ", // nf-md-video
+            FileKind::Archive => "󰀼 ", // nf-md-archive
+            FileKind::Rust => " ", // nf-dev-rust
+            FileKind::C => " ", // nf-dev-c
+            FileKind::Python => " ", // nf-dev-python
+            FileKind::JavaScript => " ", // nf-dev-javascript
+            FileKind::TypeScript => " ", // nf-dev-typescript
+            FileKind::Config => " ", // nf-seti-config
+            FileKind::Other => "󰈔 ", // nf-md-file
         }
     }
 
@@ -639,12 +1341,7 @@ impl fmt::Display for SortMode {
 }
 fn detect_icon_mode() -> IconMode {
     if let Ok(mode) = std::env::var("FREN_ICON_MODE") {
-        return match mode.to_lowercase().as_str() {
-            "ascii" => IconMode::Ascii,
-            "nerd" => IconMode::Nerd,
-            "emoji" => IconMode::Emoji,
-            _ => IconMode::Emoji,
-        };
+        return IconMode::parse(&mode).unwrap_or(IconMode::Emoji);
     }
 
     let term = std::env::var("TERM").unwrap_or_default().to_lowercase();
@@ -667,7 +1364,98 @@ fn detect_icon_mode() -> IconMode {
 pub fn quantize(v: u16) -> u16 {
     (v / 4) * 4
 }
+// what `archive_preview` holds for an archive
+pub fn list_archive(path: &Path) -> Result<Vec<ArchiveEntry>, String> {
+    archive::list(path).map_err(|e| e.to_string())
+}
 pub fn get_dimensions(path: &std::path::Path) -> Option<(u32, u32)> {
     let reader = ImageReader::open(path).ok()?;
     reader.into_dimensions().ok()
 }
+
+// rsvg-convert if available, otherwise the resvg CLI
+// A directory only this process can write to, for the images external tools
+// render; gone with everything in it once dropped. A fixed name straight in
+// /tmp could be taken or symlinked by anyone.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(request_id: u64) -> io::Result<Self> {
+        use std::os::unix::fs::DirBuilderExt;
+
+        let dir = env::temp_dir().join(format!("fren-{}-{}", std::process::id(), request_id));
+
+        // fails on anything already there, links included
+        fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn rasterize_svg(path: &Path, tmp_png: &Path, max_w: u32, max_h: u32) -> Option<image::DynamicImage> {
+    let (w, h) = (max_w.to_string(), max_h.to_string());
+
+    let rendered = std::process::Command::new("rsvg-convert")
+        .args(["--keep-aspect-ratio", "-w", &w, "-h", &h, "-f", "png", "-o"])
+        .arg(tmp_png)
+        .arg(path)
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+
+    let rendered = rendered
+        || std::process::Command::new("resvg")
+            .args(["-w", &w])
+            .arg(path)
+            .arg(tmp_png)
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+
+    // a failed run may still have left part of a file behind
+    let img = rendered.then(|| image::open(tmp_png).ok()).flatten();
+    let _ = std::fs::remove_file(tmp_png);
+
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratch_dir_is_private_and_cleaned_up() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let scratch = ScratchDir::new(u64::MAX).unwrap();
+        let dir = scratch.0.clone();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+
+        // the same name can't be handed out twice
+        assert!(ScratchDir::new(u64::MAX).is_err());
+
+        fs::write(dir.join("frame.png"), b"png").unwrap();
+        drop(scratch);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn scratch_dir_refuses_a_planted_link() {
+        let target = env::temp_dir().join(format!("fren-app-{}-target", std::process::id()));
+        let planted = env::temp_dir().join(format!("fren-{}-{}", std::process::id(), u64::MAX - 1));
+        fs::create_dir_all(&target).unwrap();
+        let _ = fs::remove_file(&planted);
+        std::os::unix::fs::symlink(&target, &planted).unwrap();
+
+        assert!(ScratchDir::new(u64::MAX - 1).is_err());
+        assert!(target.exists());
+
+        let _ = fs::remove_file(&planted);
+        let _ = fs::remove_dir_all(&target);
+    }
+}
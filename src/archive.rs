@@ -0,0 +1,1969 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, Metadata},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    os::unix::{
+        ffi::OsStringExt,
+        fs::{MetadataExt, PermissionsExt},
+    },
+    path::{Component, Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+use flate2::{
+    Compression, Crc, GzBuilder,
+    read::{DeflateDecoder, MultiGzDecoder},
+    write::{DeflateEncoder, GzEncoder},
+};
+
+//
+// ARCHIVE KIND
+//
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+    Gz,
+}
+
+// longest first so `.tar.gz` wins over `.gz`
+const SUFFIXES: [(&str, ArchiveKind); 9] = [
+    (".tar.zst", ArchiveKind::TarZst),
+    (".tar.gz", ArchiveKind::TarGz),
+    (".tar.xz", ArchiveKind::TarXz),
+    (".tzst", ArchiveKind::TarZst),
+    (".tgz", ArchiveKind::TarGz),
+    (".txz", ArchiveKind::TarXz),
+    (".tar", ArchiveKind::Tar),
+    (".zip", ArchiveKind::Zip),
+    (".gz", ArchiveKind::Gz),
+];
+
+impl ArchiveKind {
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        split_suffix(name).map(|(_, _, kind)| kind)
+    }
+}
+
+fn split_suffix(name: &str) -> Option<(&str, &str, ArchiveKind)> {
+    let lower = name.to_ascii_lowercase();
+
+    SUFFIXES
+        .iter()
+        .find(|(suffix, _)| lower.ends_with(suffix) && lower.len() > suffix.len())
+        .map(|(suffix, kind)| {
+            let (stem, ext) = name.split_at(name.len() - suffix.len());
+            (stem, ext, *kind)
+        })
+}
+
+/// Archive name without its archive extension, e.g. `photos` for `photos.tar.gz`.
+pub fn stem(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    match split_suffix(&name) {
+        Some((stem, _, _)) => stem.to_string(),
+        None => name,
+    }
+}
+
+//
+// ARCHIVE ENTRY
+//
+#[derive(Clone, Debug)]
+pub struct ArchiveEntry {
+    /// Member path inside the archive, `/`-separated, without leading `./` or trailing `/`
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Local>>,
+    pub is_dir: bool,
+    pub mode: Option<u32>,
+}
+
+impl ArchiveEntry {
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+//
+// Listing
+//
+pub fn list(path: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let kind = ArchiveKind::detect(path).ok_or_else(|| invalid("not an archive"))?;
+
+    if kind == ArchiveKind::Zip {
+        let mut file = File::open(path)?;
+        return Ok(zip_central_directory(&mut file)?
+            .into_iter()
+            .filter(|(_, record)| !record.link)
+            .map(|(entry, _)| entry)
+            .collect());
+    }
+
+    let mut entries = Vec::new();
+    walk(path, kind, &mut |entry, _| {
+        entries.push(entry.clone());
+        Ok(())
+    })?;
+
+    Ok(entries)
+}
+
+/// Immediate children of `dir` (empty for the archive root), with directories
+/// synthesised for archives that only store file paths.
+pub fn children(members: &[ArchiveEntry], dir: &str, show_hidden: bool) -> Vec<ArchiveEntry> {
+    let mut dirs: HashMap<String, usize> = HashMap::new();
+    let mut out: Vec<ArchiveEntry> = Vec::new();
+
+    for member in members {
+        let rest = if dir.is_empty() {
+            member.path.as_str()
+        } else {
+            match member.path.strip_prefix(dir).and_then(|r| r.strip_prefix('/')) {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+
+        if rest.is_empty() {
+            continue;
+        }
+
+        let (head, nested) = match rest.split_once('/') {
+            Some((head, _)) => (head, true),
+            None => (rest, false),
+        };
+
+        if !show_hidden && head.starts_with('.') {
+            continue;
+        }
+
+        if !nested && !member.is_dir {
+            out.push(member.clone());
+            continue;
+        }
+
+        let index = *dirs.entry(head.to_string()).or_insert_with(|| {
+            out.push(ArchiveEntry {
+                path: join(dir, head),
+                size: 0,
+                modified: None,
+                is_dir: true,
+                mode: None,
+            });
+            out.len() - 1
+        });
+
+        if nested {
+            out[index].size += member.size;
+        } else {
+            out[index].modified = member.modified;
+            out[index].mode = member.mode;
+        }
+    }
+
+    out.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| natord::compare_ignore_case(a.name(), b.name()))
+    });
+
+    out
+}
+
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+//
+// Extraction
+//
+fn write_member(
+    entry: &ArchiveEntry,
+    data: &mut dyn Read,
+    target: &Path,
+    progress: &mut dyn FnMut(u64) -> io::Result<()>,
+) -> io::Result<()> {
+    if entry.is_dir {
+        return fs::create_dir_all(target);
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut out = File::create(target)?;
+    copy_with_progress(data, &mut out, progress)?;
+
+    if let Some(mode) = entry.mode {
+        fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+
+    Ok(())
+}
+
+// an existing symlink anywhere along the way would redirect the write outside `dest`
+fn refuse_links(dest: &Path, rel: &Path) -> io::Result<()> {
+    let mut path = dest.to_path_buf();
+
+    for part in rel.components() {
+        path.push(part);
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(invalid(format!("refusing to write through symlink {}", path.display())));
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+// refuse absolute paths, `..` and Windows drives (`C:`) so members can't escape the destination
+fn safe_relative(path: &str) -> io::Result<PathBuf> {
+    let rel = Path::new(path);
+    let drive = path.as_bytes().get(..2).is_some_and(|p| p[0].is_ascii_alphabetic() && p[1] == b':');
+
+    if path.is_empty() || drive || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(invalid(format!("unsafe path in archive: {}", path)));
+    }
+
+    Ok(rel.to_path_buf())
+}
+
+//
+// Walking members
+//
+type Visit<'a> = dyn FnMut(&ArchiveEntry, &mut dyn Read) -> io::Result<()> + 'a;
+
+/// Visit every member in archive order. The visitor gets a reader over the
+/// member's data and may leave it unread.
+fn walk(path: &Path, kind: ArchiveKind, visit: &mut Visit) -> io::Result<()> {
+    match kind {
+        ArchiveKind::Zip => walk_zip(path, visit),
+        ArchiveKind::Tar => walk_tar(&mut Seekable(BufReader::new(File::open(path)?)), visit),
+        ArchiveKind::TarGz => walk_tar(&mut Streamed(MultiGzDecoder::new(File::open(path)?)), visit),
+        ArchiveKind::TarXz => walk_piped("xz", path, visit),
+        ArchiveKind::TarZst => walk_piped("zstd", path, visit),
+        ArchiveKind::Gz => {
+            let entry = gz_entry(path)?;
+            visit(&entry, &mut MultiGzDecoder::new(File::open(path)?))
+        }
+    }
+}
+
+// xz and zstd have no decoder in-tree, so stream through the system tools
+fn walk_piped(program: &str, path: &Path, visit: &mut Visit) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .arg("-dc")
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| missing_tool(program, e))?;
+
+    let stdout = child.stdout.take().ok_or_else(|| invalid("no decompressor output"))?;
+    let mut stream = Streamed(stdout);
+
+    let result = walk_tar(&mut stream, visit)
+        .and_then(|_| io::copy(&mut stream.0, &mut io::sink()).map(|_| ()));
+
+    if result.is_err() {
+        let _ = child.kill();
+    }
+
+    let status = child.wait()?;
+    result?;
+
+    if !status.success() {
+        return Err(invalid(format!("{} failed to decompress {}", program, path.display())));
+    }
+
+    Ok(())
+}
+
+trait Stream: Read {
+    fn skip(&mut self, n: u64) -> io::Result<()>;
+}
+
+struct Seekable(BufReader<File>);
+
+impl Read for Seekable {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Stream for Seekable {
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        let n = i64::try_from(n).map_err(|_| invalid("corrupt tar size"))?;
+        self.0.seek_relative(n)
+    }
+}
+
+struct Streamed<R>(R);
+
+impl<R: Read> Read for Streamed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Read> Stream for Streamed<R> {
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.0).take(n), &mut io::sink())?;
+
+        if skipped < n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(())
+    }
+}
+
+//
+// TAR
+//
+fn walk_tar(stream: &mut dyn Stream, visit: &mut Visit) -> io::Result<()> {
+    let mut header = [0u8; 512];
+    let mut long_name: Option<String> = None;
+    let mut pax: HashMap<String, String> = HashMap::new();
+
+    while read_block(stream, &mut header)? {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let typeflag = header[156];
+        let mut size = parse_octal(&header[124..136]);
+
+        // extended headers describe the entry that follows them
+        match typeflag {
+            b'L' | b'K' | b'x' | b'g' => {
+                let mut data = Vec::new();
+                (&mut *stream).take(size).read_to_end(&mut data)?;
+                stream.skip(padding(size))?;
+
+                match typeflag {
+                    b'L' => {
+                        long_name = Some(cstr(&data));
+                    }
+                    b'x' => {
+                        pax = parse_pax(&data);
+                    }
+                    // link targets aren't listed
+                    _ => {}
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(pax_size) = pax.get("size").and_then(|s| s.parse().ok()) {
+            size = pax_size;
+        }
+
+        let name = pax
+            .remove("path")
+            .or_else(|| long_name.take())
+            .unwrap_or_else(|| ustar_name(&header));
+
+        pax.clear();
+        long_name = None;
+
+        let is_dir = typeflag == b'5' || name.ends_with('/');
+        let is_file = matches!(typeflag, b'0' | b'\0' | b'7');
+        let path = normalize(&name);
+
+        let mut data = (&mut *stream).take(size);
+
+        // links and device nodes are neither listed nor extracted
+        if !path.is_empty() && (is_dir || is_file) {
+            let entry = ArchiveEntry {
+                path,
+                size: if is_dir { 0 } else { size },
+                modified: Local
+                    .timestamp_opt(parse_octal(&header[136..148]) as i64, 0)
+                    .single(),
+                is_dir,
+                mode: Some(parse_octal(&header[100..108]) as u32),
+            };
+
+            visit(&entry, &mut data)?;
+        }
+
+        let unread = data.limit();
+        stream.skip(unread.checked_add(padding(size)).ok_or_else(|| invalid("corrupt tar size"))?)?;
+    }
+
+    Ok(())
+}
+
+fn read_block(stream: &mut dyn Stream, block: &mut [u8; 512]) -> io::Result<bool> {
+    let mut filled = 0;
+
+    while filled < block.len() {
+        match stream.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => filled += n,
+        }
+    }
+
+    Ok(true)
+}
+
+fn padding(size: u64) -> u64 {
+    (512 - size % 512) % 512
+}
+
+fn parse_octal(field: &[u8]) -> u64 {
+    // GNU base-256 encoding for large values
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return field[1..].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    }
+
+    let text = String::from_utf8_lossy(field);
+    u64::from_str_radix(text.trim_matches(|c: char| c == '\0' || c == ' '), 8).unwrap_or(0)
+}
+
+fn cstr(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn ustar_name(header: &[u8; 512]) -> String {
+    let name = cstr(&header[0..100]);
+    let prefix = cstr(&header[345..500]);
+
+    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+        format!("{}/{}", prefix, name)
+    } else {
+        name
+    }
+}
+
+// records look like "<len> <key>=<value>\n"
+fn parse_pax(data: &[u8]) -> HashMap<String, String> {
+    let mut records = HashMap::new();
+    let mut rest = data;
+
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let len: usize = match std::str::from_utf8(&rest[..space]).ok().and_then(|s| s.parse().ok()) {
+            Some(len) if len > space && len <= rest.len() => len,
+            _ => break,
+        };
+
+        let record = String::from_utf8_lossy(&rest[space + 1..len]);
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.insert(key.to_string(), value.to_string());
+        }
+
+        rest = &rest[len..];
+    }
+
+    records
+}
+
+fn normalize(name: &str) -> String {
+    let mut name = name.trim_start_matches('/');
+
+    while let Some(rest) = name.strip_prefix("./") {
+        name = rest;
+    }
+
+    name.trim_end_matches('/').to_string()
+}
+
+//
+// ZIP
+//
+const ZIP_LOCAL: u32 = 0x0403_4b50;
+const ZIP_CENTRAL: u32 = 0x0201_4b50;
+const ZIP_END: u32 = 0x0605_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const ZIP64_END: u32 = 0x0606_4b50;
+
+struct ZipRecord {
+    method: u16,
+    flags: u16,
+    compressed: u64,
+    offset: u64,
+    link: bool,
+}
+
+fn walk_zip(path: &Path, visit: &mut Visit) -> io::Result<()> {
+    let mut file = File::open(path)?;
+
+    for (entry, record) in zip_central_directory(&mut file)? {
+        // like tar links, symlinks are neither listed nor extracted
+        if record.link {
+            continue;
+        }
+
+        if record.flags & 1 != 0 {
+            return Err(invalid(format!("{} is encrypted", entry.path)));
+        }
+
+        file.seek(SeekFrom::Start(record.offset))?;
+
+        let mut local = [0u8; 30];
+        file.read_exact(&mut local)?;
+
+        if le_u32(&local[0..4]) != ZIP_LOCAL {
+            return Err(invalid("corrupt zip local header"));
+        }
+
+        let extra = le_u16(&local[26..28]) as i64 + le_u16(&local[28..30]) as i64;
+        file.seek(SeekFrom::Current(extra))?;
+
+        let mut raw = (&mut file).take(record.compressed);
+
+        match record.method {
+            0 => visit(&entry, &mut raw)?,
+            8 => visit(&entry, &mut DeflateDecoder::new(raw))?,
+            _ if entry.is_dir => visit(&entry, &mut io::empty())?,
+            method => {
+                return Err(invalid(format!(
+                    "{} uses unsupported compression method {}",
+                    entry.path, method
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn zip_central_directory(file: &mut File) -> io::Result<Vec<(ArchiveEntry, ZipRecord)>> {
+    // the end record sits in the last 22 bytes plus an optional 64 KiB comment
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(22 + 65535);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+
+    let mut tail = vec![0u8; tail_len as usize];
+    file.read_exact(&mut tail)?;
+
+    let end = tail
+        .windows(4)
+        .rposition(|w| le_u32(w) == ZIP_END)
+        .ok_or_else(|| invalid("missing zip end of central directory"))?;
+
+    let record = field(&tail, end, 22)?;
+    let mut count = le_u16(&record[10..12]) as u64;
+    let mut cd_size = le_u32(&record[12..16]) as u64;
+    let mut cd_offset = le_u32(&record[16..20]) as u64;
+
+    if (count == 0xffff || cd_offset == 0xffff_ffff) && end >= 20 {
+        let locator = field(&tail, end - 20, 20)?;
+
+        if le_u32(&locator[0..4]) == ZIP64_LOCATOR {
+            file.seek(SeekFrom::Start(le_u64(&locator[8..16])))?;
+
+            let mut zip64 = [0u8; 56];
+            file.read_exact(&mut zip64)?;
+
+            if le_u32(&zip64[0..4]) != ZIP64_END {
+                return Err(invalid("corrupt zip64 end of central directory"));
+            }
+
+            count = le_u64(&zip64[32..40]);
+            cd_size = le_u64(&zip64[40..48]);
+            cd_offset = le_u64(&zip64[48..56]);
+        }
+    }
+
+    file.seek(SeekFrom::Start(cd_offset))?;
+
+    let mut cd = Vec::new();
+    (&mut *file).take(cd_size).read_to_end(&mut cd)?;
+
+    let mut entries = Vec::new();
+    let mut at = 0;
+
+    while entries.len() < count as usize && at < cd.len() {
+        let header = field(&cd, at, 46)?;
+
+        if le_u32(&header[0..4]) != ZIP_CENTRAL {
+            return Err(invalid("corrupt zip central directory"));
+        }
+
+        let made_by = le_u16(&header[4..6]) >> 8;
+        let flags = le_u16(&header[8..10]);
+        let method = le_u16(&header[10..12]);
+        let time = le_u16(&header[12..14]);
+        let date = le_u16(&header[14..16]);
+        let mut compressed = le_u32(&header[20..24]) as u64;
+        let mut size = le_u32(&header[24..28]) as u64;
+        let name_len = le_u16(&header[28..30]) as usize;
+        let extra_len = le_u16(&header[30..32]) as usize;
+        let comment_len = le_u16(&header[32..34]) as usize;
+        let external = le_u32(&header[38..42]);
+        let mut offset = le_u32(&header[42..46]) as u64;
+
+        let name = String::from_utf8_lossy(field(&cd, at + 46, name_len)?).into_owned();
+        let extra = field(&cd, at + 46 + name_len, extra_len)?;
+
+        let mut modified = dos_datetime(date, time);
+
+        for (id, data) in extra_fields(extra) {
+            match id {
+                // zip64 sizes, present only for fields that overflowed
+                0x0001 => {
+                    let mut values = data.chunks_exact(8).map(le_u64);
+                    if size == 0xffff_ffff {
+                        size = values.next().unwrap_or(size);
+                    }
+                    if compressed == 0xffff_ffff {
+                        compressed = values.next().unwrap_or(compressed);
+                    }
+                    if offset == 0xffff_ffff {
+                        offset = values.next().unwrap_or(offset);
+                    }
+                }
+                // extended timestamp with unix mtime
+                0x5455 if data.len() >= 5 && data[0] & 1 != 0 => {
+                    let mtime = le_u32(&data[1..5]) as i32;
+                    modified = Local.timestamp_opt(mtime as i64, 0).single().or(modified);
+                }
+                _ => {}
+            }
+        }
+
+        let is_dir = name.ends_with('/');
+        let path = normalize(&name);
+
+        if !path.is_empty() {
+            entries.push((
+                ArchiveEntry {
+                    path,
+                    size: if is_dir { 0 } else { size },
+                    modified,
+                    is_dir,
+                    // only unix-made archives carry a meaningful mode
+                    mode: (made_by == 3).then_some((external >> 16) & 0o7777),
+                },
+                ZipRecord {
+                    method,
+                    flags,
+                    compressed,
+                    offset,
+                    link: made_by == 3 && (external >> 16) & 0o170000 == 0o120000,
+                },
+            ));
+        }
+
+        at += 46 + name_len + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+fn extra_fields(mut extra: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut fields = Vec::new();
+
+    while extra.len() >= 4 {
+        let id = le_u16(&extra[0..2]);
+        let len = (le_u16(&extra[2..4]) as usize).min(extra.len() - 4);
+        fields.push((id, &extra[4..4 + len]));
+        extra = &extra[4 + len..];
+    }
+
+    fields
+}
+
+fn dos_datetime(date: u16, time: u16) -> Option<DateTime<Local>> {
+    NaiveDate::from_ymd_opt(
+        1980 + (date >> 9) as i32,
+        ((date >> 5) & 0xf) as u32,
+        (date & 0x1f) as u32,
+    )?
+    .and_hms_opt(
+        (time >> 11) as u32,
+        ((time >> 5) & 0x3f) as u32,
+        ((time & 0x1f) * 2) as u32,
+    )?
+    .and_local_timezone(Local)
+    .single()
+}
+
+//
+// GZIP (single file)
+//
+fn gz_entry(path: &Path) -> io::Result<ArchiveEntry> {
+    let decoder = MultiGzDecoder::new(File::open(path)?);
+    let header = decoder.header().ok_or_else(|| invalid("corrupt gzip header"))?;
+
+    // fall back to the archive name without its `.gz`
+    let name = header
+        .filename()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+
+    let modified = match header.mtime() {
+        0 => None,
+        mtime => Local.timestamp_opt(mtime as i64, 0).single(),
+    };
+
+    // the trailer stores the uncompressed size modulo 2^32
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::End(-4))?;
+    let mut trailer = [0u8; 4];
+    file.read_exact(&mut trailer)?;
+
+    Ok(ArchiveEntry {
+        path: normalize(&name),
+        size: le_u32(&trailer) as u64,
+        modified,
+        is_dir: false,
+        mode: None,
+    })
+}
+
+//
+// Background tasks
+//
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Overwrite,
+    Rename,
+    Skip,
+}
+
+pub enum Outcome {
+    Done(String),
+    /// Nothing was written; ask the user and run again with a policy
+    Conflict(Vec<String>),
+}
+
+#[derive(Clone)]
+pub enum ArchiveTask {
+    Extract {
+        archive: PathBuf,
+        dest: PathBuf,
+        subfolder: Option<String>,
+        /// Only this file or directory, landing in `dest` without its parents
+        member: Option<String>,
+    },
+    Create {
+        target: PathBuf,
+        sources: Vec<PathBuf>,
+    },
+}
+
+/// Reports `(done, total, current item)`; returning an error aborts the task.
+pub type Progress<'a> = dyn FnMut(u64, u64, &str) -> io::Result<()> + 'a;
+
+impl ArchiveTask {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Extract { archive, .. } => format!("Extracting {}", file_name(archive)),
+            Self::Create { target, .. } => format!("Compressing {}", file_name(target)),
+        }
+    }
+
+    pub fn run(&self, policy: Option<ConflictPolicy>, progress: &mut Progress) -> io::Result<Outcome> {
+        match self {
+            Self::Extract { archive, dest, subfolder, member } => {
+                extract_all(archive, dest, subfolder.as_deref(), member.as_deref(), policy, progress)
+            }
+            Self::Create { target, sources } => create(target, sources, policy, progress),
+        }
+    }
+}
+
+fn extract_all(
+    archive: &Path,
+    dest: &Path,
+    subfolder: Option<&str>,
+    member: Option<&str>,
+    policy: Option<ConflictPolicy>,
+    progress: &mut Progress,
+) -> io::Result<Outcome> {
+    let kind = ArchiveKind::detect(archive).ok_or_else(|| invalid("not an archive"))?;
+
+    // strip the member's parent so `a/b/c` lands in `dest/c`
+    let base = member.and_then(|m| m.rfind('/')).map(|i| i + 1).unwrap_or(0);
+    let wanted = |path: &str| {
+        member.is_none_or(|m| path == m || path.strip_prefix(m).is_some_and(|rest| rest.starts_with('/')))
+    };
+
+    let members: Vec<ArchiveEntry> = list(archive)?.into_iter().filter(|m| wanted(&m.path)).collect();
+
+    // the items that land directly in `dest`
+    let tops: Vec<String> = match subfolder {
+        Some(folder) => vec![folder.to_string()],
+        None => {
+            let mut tops: Vec<String> = Vec::new();
+            for member in &members {
+                let top = member.path[base..].split('/').next().unwrap_or_default();
+                if !tops.iter().any(|t| t == top) {
+                    tops.push(top.to_string());
+                }
+            }
+            tops
+        }
+    };
+
+    // before anything is checked against, or written to, the destination
+    for top in &tops {
+        safe_relative(top)?;
+    }
+
+    // a dangling symlink is in the way too
+    let conflicts: Vec<String> = tops
+        .iter()
+        .filter(|t| fs::symlink_metadata(dest.join(t)).is_ok())
+        .cloned()
+        .collect();
+
+    // top-level name -> new name, `None` to skip it
+    let mut renames: HashMap<String, Option<String>> = HashMap::new();
+
+    match policy {
+        None if !conflicts.is_empty() => return Ok(Outcome::Conflict(conflicts)),
+        Some(ConflictPolicy::Skip) => {
+            for name in &conflicts {
+                renames.insert(name.clone(), None);
+            }
+        }
+        Some(ConflictPolicy::Rename) => {
+            for name in &conflicts {
+                renames.insert(name.clone(), Some(unique_name(dest, name)));
+            }
+        }
+        _ => {}
+    }
+
+    let folder = match subfolder {
+        Some(folder) => match renames.get(folder) {
+            Some(None) => return Ok(Outcome::Done("Nothing extracted".to_string())),
+            Some(Some(renamed)) => Some(renamed.clone()),
+            None => Some(folder.to_string()),
+        },
+        None => None,
+    };
+
+    // zip symlinks are skipped while walking, say so rather than drop them silently
+    let links = match kind {
+        ArchiveKind::Zip => zip_central_directory(&mut File::open(archive)?)?
+            .iter()
+            .filter(|(entry, record)| record.link && wanted(&entry.path))
+            .count(),
+        _ => 0,
+    };
+
+    let total: u64 = members.iter().map(|m| m.size).sum();
+    let mut done = 0u64;
+    let mut count = 0usize;
+
+    walk(archive, kind, &mut |entry, data| {
+        if !wanted(&entry.path) {
+            return Ok(());
+        }
+
+        let mut rel = entry.path[base..].to_string();
+
+        if folder.is_none() {
+            let (top, rest) = rel.split_once('/').unwrap_or((rel.as_str(), ""));
+            match renames.get(top) {
+                Some(None) => {
+                    done += entry.size;
+                    return Ok(());
+                }
+                Some(Some(renamed)) if rest.is_empty() => rel = renamed.clone(),
+                Some(Some(renamed)) => rel = format!("{}/{}", renamed, rest),
+                None => {}
+            }
+        }
+
+        let rel = match &folder {
+            Some(folder) => Path::new(folder).join(safe_relative(&rel)?),
+            None => safe_relative(&rel)?,
+        };
+        refuse_links(dest, &rel)?;
+
+        let target = dest.join(rel);
+        write_member(entry, data, &target, &mut |n| {
+            done += n;
+            progress(done, total, &entry.path)
+        })?;
+
+        count += 1;
+        Ok(())
+    })?;
+
+    let skipped = match links {
+        0 => String::new(),
+        1 => ", skipped 1 symlink".to_string(),
+        n => format!(", skipped {} symlinks", n),
+    };
+
+    Ok(Outcome::Done(format!(
+        "Extracted {} items from {}{}",
+        count,
+        file_name(archive),
+        skipped
+    )))
+}
+
+fn create(
+    target: &Path,
+    sources: &[PathBuf],
+    policy: Option<ConflictPolicy>,
+    progress: &mut Progress,
+) -> io::Result<Outcome> {
+    let dir = target.parent().unwrap_or(Path::new("."));
+    let mut target = target.to_path_buf();
+
+    if target.exists() {
+        match policy {
+            None => return Ok(Outcome::Conflict(vec![file_name(&target)])),
+            Some(ConflictPolicy::Skip) => return Ok(Outcome::Done("Nothing compressed".to_string())),
+            Some(ConflictPolicy::Rename) => target = dir.join(unique_name(dir, &file_name(&target))),
+            Some(ConflictPolicy::Overwrite) => {}
+        }
+    }
+
+    let kind = ArchiveKind::detect(&target).ok_or_else(|| {
+        invalid("unknown archive format, use .zip, .tar, .tar.gz, .tar.xz, .tar.zst or .gz")
+    })?;
+
+    let items = collect(sources)?;
+    let total: u64 = items.iter().filter(|i| i.meta.is_file()).map(|i| i.meta.len()).sum();
+
+    // write beside the target and swap in at the end, so failures leave no half archive
+    let partial = dir.join(format!(".{}.part", file_name(&target)));
+
+    let result = write_archive(kind, &partial, &items, total, progress)
+        .and_then(|_| fs::rename(&partial, &target));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result?;
+
+    Ok(Outcome::Done(format!(
+        "Compressed {} items into {}",
+        items.len(),
+        file_name(&target)
+    )))
+}
+
+fn write_archive(
+    kind: ArchiveKind,
+    path: &Path,
+    items: &[Item],
+    total: u64,
+    progress: &mut Progress,
+) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut done = 0u64;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = ZipWriter::new(BufWriter::new(file));
+            for item in items {
+                zip.append(item, &mut |n| {
+                    done += n;
+                    progress(done, total, &item.rel)
+                })?;
+            }
+            zip.finish()
+        }
+        ArchiveKind::Gz => {
+            let [item] = items else {
+                return Err(invalid("gzip holds a single file, use .tar.gz"));
+            };
+            if !item.meta.is_file() {
+                return Err(invalid("gzip holds a single file, use .tar.gz"));
+            }
+
+            let mut out = GzBuilder::new()
+                .filename(item.rel.as_bytes())
+                .mtime(item.meta.mtime() as u32)
+                .write(BufWriter::new(file), Compression::default());
+
+            copy_with_progress(&mut File::open(&item.path)?, &mut out, &mut |n| {
+                done += n;
+                progress(done, total, &item.rel)
+            })?;
+
+            out.finish()?.flush()
+        }
+        _ => {
+            let mut tar = TarWriter { out: Sink::open(kind, file)? };
+            for item in items {
+                tar.append(item, &mut |n| {
+                    done += n;
+                    progress(done, total, &item.rel)
+                })?;
+            }
+            tar.finish()?.finish()
+        }
+    }
+}
+
+struct Item {
+    path: PathBuf,
+    rel: String,
+    meta: Metadata,
+}
+
+// depth-first, directories before their contents
+fn collect(sources: &[PathBuf]) -> io::Result<Vec<Item>> {
+    fn visit(path: PathBuf, rel: String, items: &mut Vec<Item>) -> io::Result<()> {
+        let meta = fs::symlink_metadata(&path)?;
+        let is_dir = meta.is_dir();
+        items.push(Item { path: path.clone(), rel: rel.clone(), meta });
+
+        if is_dir {
+            let mut children: Vec<_> = fs::read_dir(&path)?.filter_map(Result::ok).collect();
+            children.sort_by_key(|e| e.file_name());
+
+            for child in children {
+                let name = child.file_name().to_string_lossy().into_owned();
+                visit(child.path(), format!("{}/{}", rel, name), items)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut items = Vec::new();
+    for source in sources {
+        visit(source.clone(), file_name(source), &mut items)?;
+    }
+
+    Ok(items)
+}
+
+/// `name (1).ext`, `name (2).ext`, ... keeping multi-part archive extensions together.
+pub fn unique_name(dir: &Path, name: &str) -> String {
+    let (stem, ext) = match split_suffix(name) {
+        Some((stem, ext, _)) => (stem, ext),
+        None => match name.rfind('.') {
+            Some(i) if i > 0 => name.split_at(i),
+            _ => (name, ""),
+        },
+    };
+
+    (1..)
+        .map(|n| format!("{} ({}){}", stem, n, ext))
+        .find(|candidate| !dir.join(candidate).exists())
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn copy_with_progress(
+    src: &mut dyn Read,
+    dst: &mut dyn Write,
+    progress: &mut dyn FnMut(u64) -> io::Result<()>,
+) -> io::Result<u64> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut total = 0;
+
+    loop {
+        let n = match src.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        dst.write_all(&buf[..n])?;
+        total += n as u64;
+        progress(n as u64)?;
+    }
+}
+
+//
+// Writing TAR
+//
+enum Sink {
+    Plain(BufWriter<File>),
+    Gz(GzEncoder<BufWriter<File>>),
+    Piped(Child, ChildStdin, &'static str),
+}
+
+impl Sink {
+    fn open(kind: ArchiveKind, file: File) -> io::Result<Self> {
+        let program = match kind {
+            ArchiveKind::TarXz => "xz",
+            ArchiveKind::TarZst => "zstd",
+            ArchiveKind::TarGz => {
+                return Ok(Self::Gz(GzEncoder::new(BufWriter::new(file), Compression::default())));
+            }
+            _ => return Ok(Self::Plain(BufWriter::new(file))),
+        };
+
+        let mut child = Command::new(program)
+            .args(["-c", "-q", "-T0"])
+            .stdin(Stdio::piped())
+            .stdout(file)
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| missing_tool(program, e))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| invalid("no compressor input"))?;
+        Ok(Self::Piped(child, stdin, program))
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Plain(mut out) => out.flush(),
+            Self::Gz(out) => out.finish()?.flush(),
+            Self::Piped(mut child, stdin, program) => {
+                drop(stdin);
+                if child.wait()?.success() {
+                    Ok(())
+                } else {
+                    Err(invalid(format!("{} failed to compress", program)))
+                }
+            }
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(out) => out.write(buf),
+            Self::Gz(out) => out.write(buf),
+            Self::Piped(_, stdin, _) => stdin.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(out) => out.flush(),
+            Self::Gz(out) => out.flush(),
+            Self::Piped(_, stdin, _) => stdin.flush(),
+        }
+    }
+}
+
+struct TarWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    fn append(&mut self, item: &Item, progress: &mut dyn FnMut(u64) -> io::Result<()>) -> io::Result<()> {
+        let meta = &item.meta;
+        let file_type = meta.file_type();
+
+        let (typeflag, size, link) = if file_type.is_symlink() {
+            (b'2', 0, Some(fs::read_link(&item.path)?))
+        } else if file_type.is_dir() {
+            (b'5', 0, None)
+        } else if file_type.is_file() {
+            (b'0', meta.len(), None)
+        } else {
+            // sockets, fifos and devices
+            return Ok(());
+        };
+
+        let mut name = item.rel.clone();
+        if typeflag == b'5' {
+            name.push('/');
+        }
+
+        let link = link.map(|l| l.into_os_string().into_vec()).unwrap_or_default();
+
+        if name.len() > 100 {
+            self.long_record(b'L', name.as_bytes(), meta)?;
+        }
+        if link.len() > 100 {
+            self.long_record(b'K', &link, meta)?;
+        }
+
+        self.out.write_all(&tar_header(
+            &name.as_bytes()[..name.len().min(100)],
+            meta.mode() & 0o7777,
+            size,
+            meta.mtime().max(0) as u64,
+            typeflag,
+            &link[..link.len().min(100)],
+            meta,
+        ))?;
+
+        if typeflag == b'0' {
+            let copied = copy_with_progress(&mut File::open(&item.path)?.take(size), &mut self.out, progress)?;
+            if copied != size {
+                return Err(invalid(format!("{} changed while compressing", item.rel)));
+            }
+            self.pad(size)?;
+        }
+
+        Ok(())
+    }
+
+    // GNU long name (`L`) or link target (`K`): the real value travels in a
+    // pseudo-entry before the header, which keeps the first 100 bytes
+    fn long_record(&mut self, typeflag: u8, value: &[u8], meta: &Metadata) -> io::Result<()> {
+        let mut data = value.to_vec();
+        data.push(0);
+
+        self.out.write_all(&tar_header(b"././@LongLink", 0, data.len() as u64, 0, typeflag, b"", meta))?;
+        self.out.write_all(&data)?;
+        self.pad(data.len() as u64)
+    }
+
+    fn pad(&mut self, size: u64) -> io::Result<()> {
+        self.out.write_all(&vec![0u8; padding(size) as usize])
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&[0u8; 1024])?;
+        Ok(self.out)
+    }
+}
+
+fn tar_header(
+    name: &[u8],
+    mode: u32,
+    size: u64,
+    mtime: u64,
+    typeflag: u8,
+    link: &[u8],
+    meta: &Metadata,
+) -> [u8; 512] {
+    let mut header = [0u8; 512];
+
+    header[..name.len()].copy_from_slice(name);
+    write_octal(&mut header[100..108], mode as u64);
+    write_octal(&mut header[108..116], meta.uid().min(0o7777777) as u64);
+    write_octal(&mut header[116..124], meta.gid().min(0o7777777) as u64);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = typeflag;
+    header[157..157 + link.len()].copy_from_slice(link);
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // checksum is computed with its own field set to spaces
+    header[148..156].fill(b' ');
+    let sum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+
+    header
+}
+
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+
+    if value < 1u64 << (3 * digits) {
+        field[..digits].copy_from_slice(format!("{:0width$o}", value, width = digits).as_bytes());
+        field[digits] = 0;
+    } else {
+        // GNU base-256 for values that overflow the octal field
+        field.fill(0);
+        field[0] = 0x80;
+        for (i, byte) in value.to_be_bytes().iter().rev().enumerate() {
+            field[field.len() - 1 - i] = *byte;
+        }
+    }
+}
+
+//
+// Writing ZIP
+//
+struct ZipWriter {
+    out: BufWriter<File>,
+    central: Vec<u8>,
+    count: usize,
+}
+
+impl ZipWriter {
+    fn new(out: BufWriter<File>) -> Self {
+        Self {
+            out,
+            central: Vec::new(),
+            count: 0,
+        }
+    }
+
+    fn append(&mut self, item: &Item, progress: &mut dyn FnMut(u64) -> io::Result<()>) -> io::Result<()> {
+        // zip has no portable symlinks, so store what they point to
+        let meta = if item.meta.file_type().is_symlink() {
+            match fs::metadata(&item.path) {
+                Ok(meta) if meta.is_file() => meta,
+                _ => return Ok(()),
+            }
+        } else {
+            item.meta.clone()
+        };
+
+        if !meta.is_dir() && !meta.is_file() {
+            return Ok(());
+        }
+
+        let is_dir = meta.is_dir();
+        let name = if is_dir { format!("{}/", item.rel) } else { item.rel.clone() };
+        let method: u16 = if is_dir { 0 } else { 8 };
+        let (time, date) = dos_timestamp(meta.mtime());
+
+        let offset = self.out.stream_position()?;
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend_from_slice(&ZIP_LOCAL.to_le_bytes());
+        local.extend_from_slice(&20u16.to_le_bytes());
+        local.extend_from_slice(&0x0800u16.to_le_bytes()); // utf-8 names
+        local.extend_from_slice(&method.to_le_bytes());
+        local.extend_from_slice(&time.to_le_bytes());
+        local.extend_from_slice(&date.to_le_bytes());
+        local.extend_from_slice(&[0u8; 12]); // crc and sizes, patched below
+        local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(name.as_bytes());
+        self.out.write_all(&local)?;
+
+        let mut crc = Crc::new();
+        let mut size = 0u64;
+        let mut compressed = 0u64;
+
+        if !is_dir {
+            let start = self.out.stream_position()?;
+            let mut encoder = DeflateEncoder::new(&mut self.out, Compression::default());
+            let mut file = File::open(&item.path)?;
+            let mut buf = vec![0u8; 64 * 1024];
+
+            loop {
+                let n = file.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                crc.update(&buf[..n]);
+                encoder.write_all(&buf[..n])?;
+                size += n as u64;
+                progress(n as u64)?;
+            }
+
+            encoder.finish()?;
+            let end = self.out.stream_position()?;
+            compressed = end - start;
+
+            if compressed > u32::MAX as u64 || size > u32::MAX as u64 {
+                return Err(invalid("file too large for zip, use .tar.zst"));
+            }
+
+            self.out.seek(SeekFrom::Start(offset + 14))?;
+            self.out.write_all(&crc.sum().to_le_bytes())?;
+            self.out.write_all(&(compressed as u32).to_le_bytes())?;
+            self.out.write_all(&(size as u32).to_le_bytes())?;
+            self.out.seek(SeekFrom::Start(end))?;
+        }
+
+        if offset > u32::MAX as u64 {
+            return Err(invalid("archive too large for zip, use .tar.zst"));
+        }
+
+        let external = ((meta.mode() & 0o177777) << 16) | if is_dir { 0x10 } else { 0 };
+
+        let central = &mut self.central;
+        central.extend_from_slice(&ZIP_CENTRAL.to_le_bytes());
+        central.extend_from_slice(&((3u16 << 8) | 20).to_le_bytes()); // made by unix
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&0x0800u16.to_le_bytes());
+        central.extend_from_slice(&method.to_le_bytes());
+        central.extend_from_slice(&time.to_le_bytes());
+        central.extend_from_slice(&date.to_le_bytes());
+        central.extend_from_slice(&crc.sum().to_le_bytes());
+        central.extend_from_slice(&(compressed as u32).to_le_bytes());
+        central.extend_from_slice(&(size as u32).to_le_bytes());
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central.extend_from_slice(&[0u8; 8]); // extra, comment, disk, internal attrs
+        central.extend_from_slice(&external.to_le_bytes());
+        central.extend_from_slice(&(offset as u32).to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if self.count > 0xffff {
+            return Err(invalid("too many files for zip, use .tar.zst"));
+        }
+
+        let offset = self.out.stream_position()?;
+        self.out.write_all(&self.central)?;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&ZIP_END.to_le_bytes());
+        end.extend_from_slice(&[0u8; 4]); // disk numbers
+        end.extend_from_slice(&(self.count as u16).to_le_bytes());
+        end.extend_from_slice(&(self.count as u16).to_le_bytes());
+        end.extend_from_slice(&(self.central.len() as u32).to_le_bytes());
+        end.extend_from_slice(&(offset as u32).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.out.write_all(&end)?;
+
+        self.out.flush()
+    }
+}
+
+fn dos_timestamp(mtime: i64) -> (u16, u16) {
+    let Some(dt) = Local.timestamp_opt(mtime, 0).single().filter(|d| d.year() >= 1980) else {
+        // earliest date zip can express
+        return (0, (1 << 5) | 1);
+    };
+
+    let time = (dt.hour() << 11) | (dt.minute() << 5) | (dt.second() / 2);
+    let date = (((dt.year() - 1980) as u32) << 9) | (dt.month() << 5) | dt.day();
+
+    (time as u16, date as u16)
+}
+
+//
+// Helpers
+//
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn missing_tool(program: &str, err: io::Error) -> io::Error {
+    if err.kind() == io::ErrorKind::NotFound {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} is not installed", program))
+    } else {
+        err
+    }
+}
+
+fn field(buf: &[u8], at: usize, len: usize) -> io::Result<&[u8]> {
+    buf.get(at..at + len).ok_or_else(|| invalid("truncated archive"))
+}
+
+fn le_u16(b: &[u8]) -> u16 {
+    u16::from_le_bytes([b[0], b[1]])
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn le_u64(b: &[u8]) -> u64 {
+    u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh directory per test under the system temp dir, removed afterwards
+    struct Scratch(PathBuf);
+
+    impl std::ops::Deref for Scratch {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for Scratch {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn scratch(name: &str) -> Scratch {
+        let dir = std::env::temp_dir().join(format!("fren-archive-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+
+    fn tar_member(out: &mut Vec<u8>, name: &str, typeflag: u8, data: &[u8], meta: &Metadata) {
+        out.extend_from_slice(&tar_header(name.as_bytes(), 0o644, data.len() as u64, 0, typeflag, b"", meta));
+        out.extend_from_slice(data);
+        out.resize(out.len() + padding(data.len() as u64) as usize, 0);
+    }
+
+    fn small_tar(dir: &Path) -> Vec<u8> {
+        let meta = fs::metadata(dir).unwrap();
+        let mut tar = Vec::new();
+        tar_member(&mut tar, "./hello.txt", b'0', b"hello", &meta);
+        tar_member(&mut tar, "sub/", b'5', b"", &meta);
+        tar_member(&mut tar, "sub/nested.txt", b'0', b"nested file", &meta);
+        tar.extend_from_slice(&[0u8; 1024]);
+        tar
+    }
+
+    // stored (uncompressed) members, the simplest zip there is
+    fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let files: Vec<_> = files.iter().map(|&(name, data)| (name, data, None)).collect();
+        unix_zip(&files)
+    }
+
+    // members with a unix mode are marked as made on unix, like Info-ZIP does
+    fn unix_zip(files: &[(&str, &[u8], Option<u32>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+
+        for (name, data, mode) in files {
+            let offset = out.len() as u32;
+            let mut crc = Crc::new();
+            crc.update(data);
+
+            let mut common = Vec::new();
+            common.extend_from_slice(&0u16.to_le_bytes()); // flags
+            common.extend_from_slice(&0u16.to_le_bytes()); // stored
+            common.extend_from_slice(&[0u8; 4]); // time, date
+            common.extend_from_slice(&crc.sum().to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes()); // extra
+
+            out.extend_from_slice(&ZIP_LOCAL.to_le_bytes());
+            out.extend_from_slice(&20u16.to_le_bytes());
+            out.extend_from_slice(&common);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(data);
+
+            let made_by: u16 = if mode.is_some() { 3 << 8 | 20 } else { 20 };
+            central.extend_from_slice(&ZIP_CENTRAL.to_le_bytes());
+            central.extend_from_slice(&made_by.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&common);
+            central.extend_from_slice(&[0u8; 6]); // comment, disk, internal attrs
+            central.extend_from_slice(&(mode.unwrap_or(0) << 16).to_le_bytes());
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+
+        let cd_offset = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&ZIP_END.to_le_bytes());
+        out.extend_from_slice(&[0u8; 4]);
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&cd_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out
+    }
+
+    fn paths(entries: &[ArchiveEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.path.as_str()).collect()
+    }
+
+    fn extract(archive: &Path, dest: &Path, member: Option<&str>) -> io::Result<Outcome> {
+        let task = ArchiveTask::Extract {
+            archive: archive.to_path_buf(),
+            dest: dest.to_path_buf(),
+            subfolder: None,
+            member: member.map(str::to_string),
+        };
+        task.run(None, &mut |_, _, _| Ok(()))
+    }
+
+    #[test]
+    fn lists_tar() {
+        let dir = scratch("list-tar");
+        let path = dir.join("small.tar");
+        fs::write(&path, small_tar(&dir)).unwrap();
+
+        let entries = list(&path).unwrap();
+        assert_eq!(paths(&entries), ["hello.txt", "sub", "sub/nested.txt"]);
+        assert_eq!(entries[0].size, 5);
+        assert!(entries[1].is_dir);
+        assert_eq!(entries[2].size, 11);
+    }
+
+    #[test]
+    fn lists_tar_gz() {
+        let dir = scratch("list-tgz");
+        let path = dir.join("small.tar.gz");
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&small_tar(&dir)).unwrap();
+        fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        assert_eq!(paths(&list(&path).unwrap()), ["hello.txt", "sub", "sub/nested.txt"]);
+    }
+
+    #[test]
+    fn lists_zip() {
+        let dir = scratch("list-zip");
+        let path = dir.join("small.zip");
+        fs::write(&path, stored_zip(&[("docs/", b""), ("docs/a.txt", b"abc"), ("b.bin", b"\0\x01")])).unwrap();
+
+        let entries = list(&path).unwrap();
+        assert_eq!(paths(&entries), ["docs", "docs/a.txt", "b.bin"]);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].size, 3);
+
+        let root = children(&entries, "", true);
+        assert_eq!(paths(&root), ["docs", "b.bin"]);
+    }
+
+    #[test]
+    fn lists_gz() {
+        let dir = scratch("list-gz");
+        let path = dir.join("notes.txt.gz");
+
+        let mut gz = GzBuilder::new().filename("notes.txt").write(Vec::new(), Compression::default());
+        gz.write_all(b"some notes\n").unwrap();
+        fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        let entries = list(&path).unwrap();
+        assert_eq!(paths(&entries), ["notes.txt"]);
+        assert_eq!(entries[0].size, 11);
+    }
+
+    #[test]
+    fn safe_relative_refuses_escapes() {
+        assert!(safe_relative("a/b.txt").is_ok());
+        assert!(safe_relative("dir").is_ok());
+
+        for path in ["", "../evil", "a/../../evil", "/etc/passwd", "./a", "C:/Windows/evil", "c:evil"] {
+            assert!(safe_relative(path).is_err(), "{} was accepted", path);
+        }
+    }
+
+    #[test]
+    fn extraction_refuses_traversal() {
+        let dir = scratch("traversal");
+        let path = dir.join("evil.zip");
+        let dest = dir.join("out");
+        fs::create_dir(&dest).unwrap();
+        fs::write(&path, stored_zip(&[("../evil.txt", b"gotcha")])).unwrap();
+
+        assert!(extract(&path, &dest, None).is_err());
+        assert!(!dir.join("evil.txt").exists());
+    }
+
+    #[test]
+    fn extraction_refuses_to_write_through_symlinks() {
+        let dir = scratch("through-links");
+        let path = dir.join("nested.zip");
+        let dest = dir.join("out");
+        let outside = dir.join("outside");
+        fs::create_dir_all(dest.join("a")).unwrap();
+        fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("a/b")).unwrap();
+        fs::write(&path, stored_zip(&[("a/b/evil.txt", b"gotcha")])).unwrap();
+
+        let task = ArchiveTask::Extract {
+            archive: path.clone(),
+            dest: dest.clone(),
+            subfolder: None,
+            member: None,
+        };
+        assert!(task.run(Some(ConflictPolicy::Overwrite), &mut |_, _, _| Ok(())).is_err());
+        assert!(!outside.join("evil.txt").exists());
+
+        // a dangling link at the top is a conflict, not a place to write through
+        let path = dir.join("top.zip");
+        fs::write(&path, stored_zip(&[("c", b"gotcha")])).unwrap();
+        std::os::unix::fs::symlink(outside.join("c"), dest.join("c")).unwrap();
+
+        assert!(matches!(extract(&path, &dest, None), Ok(Outcome::Conflict(names)) if names == ["c"]));
+        assert!(!outside.join("c").exists());
+    }
+
+    #[test]
+    fn zip_symlinks_are_skipped() {
+        let dir = scratch("zip-links");
+        let path = dir.join("links.zip");
+        let dest = dir.join("out");
+        fs::create_dir(&dest).unwrap();
+        fs::write(
+            &path,
+            unix_zip(&[("file.txt", b"hello", Some(0o100644)), ("link", b"/etc/passwd", Some(0o120777))]),
+        )
+        .unwrap();
+
+        assert_eq!(paths(&list(&path).unwrap()), ["file.txt"]);
+        assert!(matches!(extract(&path, &dest, None), Ok(Outcome::Done(msg)) if msg.ends_with("skipped 1 symlink")));
+        assert_eq!(fs::read(dest.join("file.txt")).unwrap(), b"hello");
+        assert!(fs::symlink_metadata(dest.join("link")).is_err());
+    }
+
+    #[test]
+    fn extracts_one_member_without_its_parents() {
+        let dir = scratch("member");
+        let path = dir.join("small.tar");
+        fs::write(&path, small_tar(&dir)).unwrap();
+
+        assert!(matches!(extract(&path, &dir, Some("sub")), Ok(Outcome::Done(_))));
+        assert_eq!(fs::read(dir.join("sub/nested.txt")).unwrap(), b"nested file");
+        assert!(!dir.join("hello.txt").exists());
+
+        // a second paste asks first
+        assert!(matches!(extract(&path, &dir, Some("sub/nested.txt")), Ok(Outcome::Done(_))));
+        assert_eq!(fs::read(dir.join("nested.txt")).unwrap(), b"nested file");
+        assert!(matches!(extract(&path, &dir, Some("sub/nested.txt")), Ok(Outcome::Conflict(_))));
+    }
+
+    #[test]
+    fn truncated_tar_header_is_an_error() {
+        let dir = scratch("truncated-tar");
+        let path = dir.join("short.tar");
+        fs::write(&path, &small_tar(&dir)[..300]).unwrap();
+
+        assert!(list(&path).is_err());
+    }
+
+    #[test]
+    fn huge_tar_size_is_an_error() {
+        let dir = scratch("huge-tar");
+        let meta = fs::metadata(&dir).unwrap();
+
+        let mut header = tar_header(b"big.bin", 0o644, 0, 0, b'0', b"", &meta);
+        header[124..136].fill(0xff);
+        let mut tar = header.to_vec();
+        tar.extend_from_slice(&[0u8; 1024]);
+
+        let path = dir.join("huge.tar");
+        fs::write(&path, &tar).unwrap();
+        assert!(list(&path).is_err());
+
+        // the same through a stream that can't seek
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&tar).unwrap();
+        let path = dir.join("huge.tar.gz");
+        fs::write(&path, gz.finish().unwrap()).unwrap();
+        assert!(list(&path).is_err());
+    }
+
+    #[test]
+    fn corrupt_zip_is_an_error() {
+        let dir = scratch("corrupt-zip");
+        let zip = stored_zip(&[("a.txt", b"abc")]);
+        let path = dir.join("bad.zip");
+
+        // no end of central directory
+        fs::write(&path, &zip[..zip.len() - 22]).unwrap();
+        assert!(list(&path).is_err());
+
+        // central directory cut short, end record intact
+        let cd_start = 30 + 5 + 3;
+        let mut cut = zip[..cd_start + 20].to_vec();
+        cut.extend_from_slice(&zip[zip.len() - 22..]);
+        fs::write(&path, &cut).unwrap();
+        assert!(list(&path).is_err());
+
+        // central directory pointing at the wrong place
+        let mut moved = zip.clone();
+        let end = moved.len() - 22;
+        moved[end + 16..end + 20].copy_from_slice(&1u32.to_le_bytes());
+        fs::write(&path, &moved).unwrap();
+        assert!(list(&path).is_err());
+
+        // local header that isn't one
+        let mut local = zip.clone();
+        local[0] = b'X';
+        fs::write(&path, &local).unwrap();
+        assert!(extract(&path, &dir, None).is_err());
+
+        fs::write(&path, b"").unwrap();
+        assert!(list(&path).is_err());
+    }
+
+    #[test]
+    fn truncated_gz_is_an_error() {
+        let dir = scratch("truncated-gz");
+        let path = dir.join("short.gz");
+
+        fs::write(&path, b"\x1f\x8b").unwrap();
+        assert!(list(&path).is_err());
+    }
+
+    fn create(target: &Path, sources: &[PathBuf], policy: Option<ConflictPolicy>) -> io::Result<Outcome> {
+        let task = ArchiveTask::Create {
+            target: target.to_path_buf(),
+            sources: sources.to_vec(),
+        };
+        task.run(policy, &mut |_, _, _| Ok(()))
+    }
+
+    // a small tree with a nested file, an empty file and a name too long for a plain tar header
+    fn sample_tree(dir: &Path) -> PathBuf {
+        let root = dir.join("tree");
+        let deep = root.join("d".repeat(60)).join("e".repeat(60));
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(root.join("a.txt"), "alpha\n".repeat(1000)).unwrap();
+        fs::write(root.join("empty"), b"").unwrap();
+        fs::write(deep.join("long.txt"), b"far down").unwrap();
+        root
+    }
+
+    fn round_trip(name: &str) {
+        let dir = scratch(&format!("round-trip-{}", name));
+        let root = sample_tree(&dir);
+        let target = dir.join(name);
+
+        assert!(matches!(create(&target, std::slice::from_ref(&root), None), Ok(Outcome::Done(_))));
+
+        // written beside the target, then renamed over it
+        assert!(target.is_file());
+        assert!(!dir.join(format!(".{}.part", name)).exists());
+
+        let long = format!("tree/{}/{}/long.txt", "d".repeat(60), "e".repeat(60));
+        let entries = list(&target).unwrap();
+        let listed = paths(&entries);
+        assert!(listed.contains(&"tree/a.txt"));
+        assert!(listed.contains(&"tree/empty"));
+        assert!(listed.contains(&long.as_str()));
+
+        let out = dir.join("out");
+        fs::create_dir(&out).unwrap();
+        assert!(matches!(extract(&target, &out, None), Ok(Outcome::Done(_))));
+
+        assert_eq!(fs::read(out.join("tree/a.txt")).unwrap(), fs::read(root.join("a.txt")).unwrap());
+        assert_eq!(fs::read(out.join("tree/empty")).unwrap(), b"");
+        assert_eq!(fs::read(out.join(&long)).unwrap(), b"far down");
+    }
+
+    #[test]
+    fn round_trips_tar() {
+        round_trip("tree.tar");
+    }
+
+    #[test]
+    fn round_trips_tar_gz() {
+        round_trip("tree.tar.gz");
+    }
+
+    #[test]
+    fn round_trips_zip() {
+        round_trip("tree.zip");
+    }
+
+    #[test]
+    fn round_trips_gz() {
+        let dir = scratch("round-trip-gz");
+        let source = dir.join("notes.txt");
+        fs::write(&source, b"some notes\n").unwrap();
+
+        let target = dir.join("notes.txt.gz");
+        assert!(matches!(create(&target, std::slice::from_ref(&source), None), Ok(Outcome::Done(_))));
+        assert_eq!(paths(&list(&target).unwrap()), ["notes.txt"]);
+
+        let out = dir.join("out");
+        fs::create_dir(&out).unwrap();
+        assert!(matches!(extract(&target, &out, None), Ok(Outcome::Done(_))));
+        assert_eq!(fs::read(out.join("notes.txt")).unwrap(), b"some notes\n");
+    }
+
+    #[test]
+    fn zip_headers_are_patched() {
+        let dir = scratch("zip-patch");
+        let source = dir.join("data.txt");
+        let data = "patched after the data is written\n".repeat(100);
+        fs::write(&source, &data).unwrap();
+
+        let target = dir.join("data.zip");
+        create(&target, std::slice::from_ref(&source), None).unwrap();
+        let zip = fs::read(&target).unwrap();
+
+        let mut crc = Crc::new();
+        crc.update(data.as_bytes());
+
+        // the local header is written before the sizes are known
+        let (_, record) = zip_central_directory(&mut File::open(&target).unwrap()).unwrap().remove(0);
+        assert_eq!(le_u32(&zip[14..18]), crc.sum());
+        assert_eq!(le_u32(&zip[18..22]) as u64, record.compressed);
+        assert_eq!(le_u32(&zip[22..26]) as usize, data.len());
+        assert!(record.compressed < data.len() as u64);
+    }
+
+    #[test]
+    fn failed_create_leaves_nothing_behind() {
+        let dir = scratch("create-fails");
+        let sources = [dir.join("a"), dir.join("b")];
+        for source in &sources {
+            fs::write(source, b"x").unwrap();
+        }
+
+        let target = dir.join("both.gz");
+        assert!(create(&target, &sources, None).is_err());
+        assert!(!target.exists());
+        assert!(!dir.join(".both.gz.part").exists());
+    }
+
+    #[test]
+    fn create_asks_before_overwriting() {
+        let dir = scratch("create-conflict");
+        let source = dir.join("a.txt");
+        fs::write(&source, b"a").unwrap();
+
+        let target = dir.join("a.tar");
+        fs::write(&target, b"old").unwrap();
+
+        assert!(matches!(create(&target, std::slice::from_ref(&source), None), Ok(Outcome::Conflict(_))));
+        assert_eq!(fs::read(&target).unwrap(), b"old");
+
+        create(&target, std::slice::from_ref(&source), Some(ConflictPolicy::Rename)).unwrap();
+        assert_eq!(paths(&list(&dir.join("a (1).tar")).unwrap()), ["a.txt"]);
+    }
+
+    #[test]
+    fn long_link_targets_are_kept() {
+        let dir = scratch("long-link");
+        let root = dir.join("links");
+        fs::create_dir(&root).unwrap();
+
+        let far = "t".repeat(150);
+        std::os::unix::fs::symlink(&far, root.join("link")).unwrap();
+
+        let target = dir.join("links.tar");
+        create(&target, std::slice::from_ref(&root), None).unwrap();
+        let tar = fs::read(&target).unwrap();
+
+        // the `K` record carries the whole target, the link header follows it
+        let mut at = 0;
+        let mut long_link = None;
+        while at + 512 <= tar.len() && tar[at..at + 512].iter().any(|&b| b != 0) {
+            let header: &[u8; 512] = tar[at..at + 512].try_into().unwrap();
+            let size = parse_octal(&header[124..136]);
+            let data = &tar[at + 512..at + 512 + size as usize];
+
+            match header[156] {
+                b'K' => long_link = Some(cstr(data)),
+                b'2' => {
+                    assert_eq!(ustar_name(header), "links/link");
+                    assert_eq!(long_link.take().as_deref(), Some(far.as_str()));
+                    return;
+                }
+                _ => {}
+            }
+            at += 512 + (size + padding(size)) as usize;
+        }
+
+        panic!("no symlink in the archive");
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    hash::Hash,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::SystemTime,
+};
+
+use lru::LruCache;
+
+use crate::event::AppEvent;
+
+/// mtime and size, enough to notice that a file changed on disk.
+#[derive(Hash, Eq, PartialEq, Clone, Copy)]
+pub struct FileStamp {
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Self {
+        match fs::metadata(path) {
+            Ok(meta) => Self {
+                modified: meta.modified().ok(),
+                size: meta.len(),
+            },
+            Err(_) => Self {
+                modified: None,
+                size: 0,
+            },
+        }
+    }
+}
+
+//
+// One value for the hovered file
+//
+/// Holds a value for one version of one file, e.g. the pdfinfo of the hovered PDF.
+pub struct FileCache<T> {
+    entry: Option<(PathBuf, FileStamp, T)>,
+}
+
+impl<T> FileCache<T> {
+    pub fn new() -> Self {
+        Self { entry: None }
+    }
+
+    /// The value for `path`, computed again when the path or the file changes.
+    pub fn get_or_insert_with(&mut self, path: &Path, f: impl FnOnce() -> T) -> &T {
+        let stamp = FileStamp::of(path);
+        let fresh = self
+            .entry
+            .as_ref()
+            .is_some_and(|(p, s, _)| p == path && *s == stamp);
+
+        if !fresh {
+            self.entry = Some((path.to_path_buf(), stamp, f()));
+        }
+
+        &self.entry.as_ref().unwrap().2
+    }
+
+    pub fn invalidate(&mut self, path: &Path) {
+        if self.entry.as_ref().is_some_and(|(p, _, _)| p == path) {
+            self.entry = None;
+        }
+    }
+}
+
+//
+// One value for the hovered file, computed in the background
+//
+/// A `FileCache` for values too slow to compute while drawing, like running
+/// `pdfinfo`. The value is worked out on a thread and shows up on a later draw.
+pub struct BackgroundCache<T> {
+    entry: Option<(PathBuf, FileStamp, T)>,
+    pending: Option<(PathBuf, FileStamp)>,
+    tx: Sender<(PathBuf, FileStamp, T)>,
+    rx: Receiver<(PathBuf, FileStamp, T)>,
+    waker: Sender<AppEvent>,
+}
+
+impl<T: Send + 'static> BackgroundCache<T> {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            entry: None,
+            pending: None,
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    /// The value for `path`, or `None` while `f` is still working it out.
+    pub fn request(&mut self, path: &Path, f: impl FnOnce(&Path) -> T + Send + 'static) -> Option<&T> {
+        // results for files that are no longer wanted are dropped
+        while let Ok((done, stamp, value)) = self.rx.try_recv() {
+            if self.pending.as_ref().is_some_and(|(p, s)| *p == done && *s == stamp) {
+                self.pending = None;
+                self.entry = Some((done, stamp, value));
+            }
+        }
+
+        let stamp = FileStamp::of(path);
+        let fresh = self
+            .entry
+            .as_ref()
+            .is_some_and(|(p, s, _)| p == path && *s == stamp);
+
+        if fresh {
+            return self.entry.as_ref().map(|(_, _, value)| value);
+        }
+
+        let started = self.pending.as_ref().is_some_and(|(p, s)| p == path && *s == stamp);
+        if !started {
+            let path = path.to_path_buf();
+            let tx = self.tx.clone();
+            let waker = self.waker.clone();
+            self.pending = Some((path.clone(), stamp));
+
+            thread::spawn(move || {
+                let value = f(&path);
+                let _ = tx.send((path, stamp, value));
+                let _ = waker.send(AppEvent::Worker);
+            });
+        }
+
+        None
+    }
+
+    pub fn invalidate(&mut self, path: &Path) {
+        if self.entry.as_ref().is_some_and(|(p, _, _)| p == path) {
+            self.entry = None;
+        }
+        if self.pending.as_ref().is_some_and(|(p, _)| p == path) {
+            self.pending = None;
+        }
+    }
+}
+
+//
+// Bounded preview cache
+//
+#[derive(Clone, Copy)]
+pub enum CacheLimit {
+    Entries(NonZeroUsize),
+    Megabytes(usize),
+}
+
+impl CacheLimit {
+    /// `"128"` entries or a budget like `"256MB"`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+
+        match value.strip_suffix("MB").or(value.strip_suffix("mb")) {
+            Some(mb) => mb.trim().parse().ok().filter(|&mb| mb > 0).map(Self::Megabytes),
+            None => value.parse().ok().and_then(NonZeroUsize::new).map(Self::Entries),
+        }
+    }
+}
+
+impl Default for CacheLimit {
+    fn default() -> Self {
+        Self::Entries(NonZeroUsize::new(128).unwrap())
+    }
+}
+
+/// LRU bounded by entry count or by the estimated size of what it holds.
+pub struct PreviewCache<K: Hash + Eq, V> {
+    lru: LruCache<K, (V, usize)>,
+    limit: CacheLimit,
+    bytes: usize,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> PreviewCache<K, V> {
+    pub fn new(limit: CacheLimit) -> Self {
+        let lru = match limit {
+            CacheLimit::Entries(n) => LruCache::new(n),
+            CacheLimit::Megabytes(_) => LruCache::unbounded(),
+        };
+
+        Self { lru, limit, bytes: 0 }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.lru.get(key).map(|(value, _)| value.clone())
+    }
+
+    pub fn put(&mut self, key: K, value: V, bytes: usize) {
+        self.bytes += bytes;
+
+        // push hands back whatever it replaced or evicted
+        if let Some((_, (_, old))) = self.lru.push(key, (value, bytes)) {
+            self.bytes -= old;
+        }
+
+        if let CacheLimit::Megabytes(mb) = self.limit {
+            // always keep the newest, however big
+            while self.bytes > mb * 1024 * 1024 && self.lru.len() > 1 {
+                match self.lru.pop_lru() {
+                    Some((_, (_, old))) => self.bytes -= old,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    pub fn remove_where(&mut self, stale: impl Fn(&K) -> bool) {
+        let keys: Vec<K> = self.lru.iter().map(|(k, _)| k).filter(|k| stale(k)).cloned().collect();
+
+        for key in keys {
+            if let Some((_, old)) = self.lru.pop(&key) {
+                self.bytes -= old;
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+};
+
+use crate::app::{IconMode, SortMode};
+
+pub const USAGE: &str = "\
+Usage: fren [OPTIONS] [PATH]
+
+Browse PATH, or the current directory. When PATH is a file, its
+directory is opened with the file selected.
+
+Options:
+      --config <FILE>    Read keymaps and settings from FILE
+      --theme <FILE>     Read colors from FILE
+      --icons <MODE>     Icon set: ascii, emoji or nerd
+      --show-hidden      Show dotfiles
+      --sort <MODE>      Sort by name, size or modified
+      --cwd-file <FILE>  On quit-and-cd, write the last directory to FILE
+      --shell-init <SH>  Print a wrapper function that cds to where fren
+                         left off, for bash, zsh or fish
+      --choose-files <OUT>
+                         Pick files and write their paths to OUT (- for stdout)
+      --choose-dir <OUT> Pick a directory and write its path to OUT
+  -0, --null             Separate chosen paths with NUL instead of newlines
+  -h, --help             Print this help
+  -V, --version          Print the version";
+
+/// Settings from the command line, each overriding the config or the default.
+#[derive(Default)]
+pub struct Args {
+    pub path: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub theme: Option<PathBuf>,
+    pub icons: Option<IconMode>,
+    pub show_hidden: bool,
+    pub sort: Option<SortMode>,
+    pub cwd_file: Option<PathBuf>,
+    pub choose: Option<Chooser>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChooseMode {
+    Files,
+    Dir,
+}
+
+/// Picker mode: where the chosen paths go and how they're separated.
+pub struct Chooser {
+    pub mode: ChooseMode,
+    /// `None` for stdout
+    pub output: Option<PathBuf>,
+    pub null: bool,
+}
+
+impl Chooser {
+    pub fn write(&self, paths: &[PathBuf], out: &mut dyn Write) -> io::Result<()> {
+        let separator = if self.null { b'\0' } else { b'\n' };
+
+        for path in paths {
+            out.write_all(path.as_os_str().as_bytes())?;
+            out.write_all(&[separator])?;
+        }
+        out.flush()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+pub enum Command {
+    Run(Args),
+    Help,
+    Version,
+    ShellInit(Shell),
+}
+
+/// Parse the arguments after the program name. Errors are ready to print.
+pub fn parse(mut argv: impl Iterator<Item = OsString>) -> Result<Command, String> {
+    let mut args = Args::default();
+    let mut only_paths = false;
+    let mut null = false;
+
+    while let Some(arg) = argv.next() {
+        let flag = arg.to_string_lossy().into_owned();
+
+        // --name=value works as well as --name value
+        let (name, inline) = match flag.split_once('=') {
+            Some((name, value)) if name.starts_with("--") && !only_paths => (name.to_string(), Some(value.to_string())),
+            _ => (flag.clone(), None),
+        };
+
+        let mut value = |what: &str| -> Result<OsString, String> {
+            match inline.clone() {
+                Some(value) => Ok(value.into()),
+                None => argv.next().ok_or_else(|| format!("{} needs a {}", name, what)),
+            }
+        };
+
+        match name.as_str() {
+            _ if only_paths || !name.starts_with('-') || name == "-" => {
+                if args.path.is_some() {
+                    return Err(format!("unexpected argument '{}'", flag));
+                }
+                args.path = Some(PathBuf::from(arg));
+            }
+            "--" => only_paths = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--show-hidden" => args.show_hidden = true,
+            "--cwd-file" => args.cwd_file = Some(PathBuf::from(value("file")?)),
+            "--choose-files" | "--choose-dir" => {
+                if args.choose.is_some() {
+                    return Err("--choose-files and --choose-dir can't be combined".to_string());
+                }
+
+                let output = value("file, or - for stdout")?;
+                args.choose = Some(Chooser {
+                    mode: if name == "--choose-dir" { ChooseMode::Dir } else { ChooseMode::Files },
+                    output: (output != "-").then(|| PathBuf::from(output)),
+                    null: false,
+                });
+            }
+            "-0" | "--null" => null = true,
+            "--shell-init" => {
+                let shell = value("shell")?.to_string_lossy().into_owned();
+                return match shell.as_str() {
+                    "bash" => Ok(Command::ShellInit(Shell::Bash)),
+                    "zsh" => Ok(Command::ShellInit(Shell::Zsh)),
+                    "fish" => Ok(Command::ShellInit(Shell::Fish)),
+                    _ => Err(format!("unknown shell '{}', use bash, zsh or fish", shell)),
+                };
+            }
+            "--config" => args.config = Some(existing_file(value("file")?, "config")?),
+            "--theme" => args.theme = Some(existing_file(value("file")?, "theme")?),
+            "--icons" => {
+                let mode = value("mode")?.to_string_lossy().into_owned();
+                args.icons = Some(
+                    IconMode::parse(&mode).ok_or_else(|| format!("unknown icon mode '{}', use ascii, emoji or nerd", mode))?,
+                );
+            }
+            "--sort" => {
+                let mode = value("mode")?.to_string_lossy().into_owned();
+                args.sort = Some(
+                    SortMode::parse(&mode).ok_or_else(|| format!("unknown sort mode '{}', use name, size or modified", mode))?,
+                );
+            }
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+
+    if let Some(path) = &args.path
+        && !path.exists()
+    {
+        return Err(format!("{}: no such file or directory", path.display()));
+    }
+
+    match &mut args.choose {
+        Some(chooser) => chooser.null = null,
+        None if null => return Err("--null only applies to --choose-files and --choose-dir".to_string()),
+        None => {}
+    }
+
+    // better to find out before picking than after
+    if let Some(output) = args.choose.as_ref().and_then(|c| c.output.as_deref())
+        && !output.parent().is_none_or(|dir| dir.as_os_str().is_empty() || dir.is_dir())
+    {
+        return Err(format!("can't write to {}: no such directory", output.display()));
+    }
+
+    Ok(Command::Run(args))
+}
+
+fn existing_file(path: OsString, what: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!("{} file {} not found", what, path.display()))
+    }
+}
+
+//
+// Shell integration
+//
+/// A `fren` function for the shell's rc file, e.g. `eval "$(fren --shell-init zsh)"`.
+/// It runs fren with a temporary `--cwd-file` and cds to whatever was written there.
+pub fn shell_init(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash | Shell::Zsh => r#"fren() {
+    local tmp code dir
+    tmp="$(mktemp -t fren-cwd.XXXXXX)" || return
+    command fren --cwd-file "$tmp" "$@"
+    code=$?
+    dir="$(cat -- "$tmp")"
+    rm -f -- "$tmp"
+    if [ -n "$dir" ] && [ "$dir" != "$PWD" ]; then
+        builtin cd -- "$dir" || return
+    fi
+    return $code
+}"#,
+        Shell::Fish => r#"function fren
+    set -l tmp (mktemp -t fren-cwd.XXXXXX); or return
+    command fren --cwd-file $tmp $argv
+    set -l code $status
+    set -l dir (cat -- $tmp)
+    rm -f -- $tmp
+    if test -n "$dir"; and test "$dir" != "$PWD"
+        cd -- $dir
+    end
+    return $code
+end"#,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn run(argv: &[&str]) -> Result<Command, String> {
+        parse(argv.iter().map(OsString::from))
+    }
+
+    fn args(argv: &[&str]) -> Args {
+        match run(argv) {
+            Ok(Command::Run(args)) => args,
+            Ok(_) => panic!("{:?} didn't parse to a run", argv),
+            Err(e) => panic!("{:?} failed: {}", argv, e),
+        }
+    }
+
+    fn error(argv: &[&str]) -> String {
+        match run(argv) {
+            Err(e) => e,
+            Ok(_) => panic!("{:?} was accepted", argv),
+        }
+    }
+
+    #[test]
+    fn values_follow_an_equals_sign_or_come_next() {
+        for argv in [&["--sort=size", "--icons=nerd"][..], &["--sort", "size", "--icons", "nerd"]] {
+            let args = args(argv);
+            assert!(matches!(args.sort, Some(SortMode::Size)));
+            assert_eq!(args.icons, Some(IconMode::Nerd));
+        }
+
+        // only the first `=` splits, the value may hold more
+        assert_eq!(args(&["--cwd-file=/tmp/a=b"]).cwd_file, Some(PathBuf::from("/tmp/a=b")));
+    }
+
+    #[test]
+    fn double_dash_ends_the_options() {
+        // a directory named like an option
+        let name = format!("--sort=size-{}", std::process::id());
+        let dir = std::env::temp_dir().join(&name);
+        fs::create_dir_all(&dir).unwrap();
+
+        let parsed = args(&["--show-hidden", "--", dir.to_str().unwrap()]);
+        let _ = fs::remove_dir(&dir);
+
+        assert!(parsed.show_hidden);
+        assert_eq!(parsed.path.as_deref(), Some(dir.as_path()));
+        assert!(parsed.sort.is_none());
+        assert!(error(&["--sort=size", "--", "--sort=size"]).contains("no such file or directory"));
+
+        // a second path is still one too many
+        assert!(error(&["--", "/", "/"]).contains("unexpected argument"));
+    }
+
+    #[test]
+    fn missing_values_and_paths_are_reported() {
+        assert_eq!(error(&["--cwd-file"]), "--cwd-file needs a file");
+        assert_eq!(error(&["--choose-files"]), "--choose-files needs a file, or - for stdout");
+        assert!(error(&["/no/such/fren/path"]).contains("no such file or directory"));
+        assert!(args(&[]).path.is_none());
+    }
+
+    #[test]
+    fn unknown_options_and_modes_are_errors() {
+        assert_eq!(error(&["--frobnicate"]), "unknown option '--frobnicate'");
+        assert_eq!(error(&["-x"]), "unknown option '-x'");
+        assert!(error(&["--sort", "colour"]).contains("unknown sort mode 'colour'"));
+        assert!(error(&["--shell-init", "csh"]).contains("unknown shell 'csh'"));
+    }
+
+    #[test]
+    fn null_needs_a_chooser() {
+        assert!(error(&["--null"]).contains("--null only applies"));
+        assert!(error(&["-0"]).contains("--null only applies"));
+
+        let args = args(&["-0", "--choose-files", "-"]);
+        let chooser = args.choose.unwrap();
+        assert!(chooser.null && chooser.output.is_none());
+        assert!(chooser.mode == ChooseMode::Files);
+
+        assert!(error(&["--choose-dir", "-", "--choose-files", "-"]).contains("can't be combined"));
+    }
+
+    #[test]
+    fn shell_init_wraps_fren_with_a_cwd_file() {
+        assert!(matches!(run(&["--shell-init", "fish"]), Ok(Command::ShellInit(Shell::Fish))));
+
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = shell_init(shell);
+            assert!(script.contains("command fren --cwd-file"));
+            assert!(script.contains("rm -f --"));
+        }
+
+        assert!(shell_init(Shell::Bash).starts_with("fren() {"));
+        assert!(shell_init(Shell::Fish).starts_with("function fren"));
+    }
+}
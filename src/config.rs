@@ -1,7 +1,13 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::cache::CacheLimit;
 
 pub struct Keymaps {
     pub quit: String,
+    pub quit_cd: String,
     pub create_file: String,
     pub create_folder: String,
     pub rename: String,
@@ -15,10 +21,35 @@ pub struct Keymaps {
     pub focus: String,
     pub pin: String,
     pub unpin: String,
+    pub preview_down: String,
+    pub preview_up: String,
+    pub preview_half_down: String,
+    pub preview_half_up: String,
+    pub preview_top: String,
+    pub preview_bottom: String,
+    pub next_page: String,
+    pub prev_page: String,
+    pub preview_raw: String,
+    pub toggle_exif: String,
+    pub tree_expand: String,
+    pub tree_collapse: String,
+    pub mark: String,
+    pub extract: String,
+    pub compress: String,
+    pub messages: String,
+    pub choose: String,
+}
+
+// external command for files matching a MIME type or file name glob
+pub struct Previewer {
+    pub pattern: String,
+    pub command: String,
 }
 
 pub struct Config {
     pub keymaps: Keymaps,
+    pub previewers: Vec<Previewer>,
+    pub preview_cache: CacheLimit,
 }
 
 impl Config {
@@ -36,6 +67,7 @@ impl Config {
 
         if !config_path.exists() {
             let default_config = "quit = \"q\"\n\
+                 quit_cd = \"Q\"\n\
                  open = \"o\"\n\
                  focus = \"tab\"\n\
                  copy = \"c\"\n\
@@ -48,33 +80,84 @@ impl Config {
                  create_folder = \"f\"\n\
                  rename = \"r\"\n\
                  pin = \"u\"\n\
-                 unpin = \"i\"\n";
+                 unpin = \"i\"\n\
+                 preview_down = \"J\"\n\
+                 preview_up = \"K\"\n\
+                 preview_half_down = \"D\"\n\
+                 preview_half_up = \"U\"\n\
+                 preview_top = \"g\"\n\
+                 preview_bottom = \"G\"\n\
+                 next_page = \"]\"\n\
+                 prev_page = \"[\"\n\
+                 preview_raw = \"m\"\n\
+                 toggle_exif = \"I\"\n\
+                 tree_expand = \"+\"\n\
+                 tree_collapse = \"-\"\n\
+                 mark = \"space\"\n\
+                 extract = \"e\"\n\
+                 compress = \"z\"\n\
+                 messages = \"L\"\n\
+                 choose = \"C\"\n\
+                 \n\
+                 # Rendered previews to keep: a count, or a memory budget like \"256MB\"\n\
+                 preview_cache = \"128\"\n\
+                 \n\
+                 # Commands get the path, width and height, and print ANSI text\n\
+                 # or the path of an image to show\n\
+                 # [previewers]\n\
+                 # \"*.md\" = \"glow -s dark\"\n\
+                 # \"application/json\" = \"jq -C .\"\n";
 
             fs::write(&config_path, default_config).expect("Failed to create default config.toml");
         }
     }
 
-    // Load config from ~/.config/alice/config.toml
-    pub fn load() -> Self {
-        Self::ensure_config_exists();
+    // Load config from ~/.config/alice/config.toml, or from `file` when given
+    pub fn load(file: Option<&Path>) -> Self {
+        let path = match file {
+            Some(file) => file.to_path_buf(),
+            None => {
+                Self::ensure_config_exists();
 
-        let path = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("fren")
-            .join("config.toml");
+                dirs::config_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join("fren")
+                    .join("config.toml")
+            }
+        };
 
         let mut config = Self::default();
 
         if let Ok(content) = fs::read_to_string(path) {
+            let mut section = String::new();
+
             for line in content.lines() {
                 let line = line.trim();
 
+                if line.starts_with('#') {
+                    continue;
+                }
+
+                if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    section = name.trim().to_string();
+                    continue;
+                }
+
                 if let Some((key, value)) = line.split_once('=') {
                     let key = key.trim();
                     let value = value.trim().trim_matches('"');
 
+                    if section == "previewers" {
+                        config.previewers.push(Previewer {
+                            pattern: key.trim_matches('"').to_string(),
+                            command: value.to_string(),
+                        });
+                        continue;
+                    }
+
                     match key {
                         "quit" => config.keymaps.quit = value.to_string(),
+                        "quit_cd" => config.keymaps.quit_cd = value.to_string(),
                         "create_file" => config.keymaps.create_file = value.to_string(),
                         "create_folder" => config.keymaps.create_folder = value.to_string(),
                         "rename" => config.keymaps.rename = value.to_string(),
@@ -88,6 +171,28 @@ impl Config {
                         "focus" => config.keymaps.focus = value.to_string(),
                         "pin" => config.keymaps.pin = value.to_string(),
                         "unpin" => config.keymaps.unpin = value.to_string(),
+                        "preview_down" => config.keymaps.preview_down = value.to_string(),
+                        "preview_up" => config.keymaps.preview_up = value.to_string(),
+                        "preview_half_down" => config.keymaps.preview_half_down = value.to_string(),
+                        "preview_half_up" => config.keymaps.preview_half_up = value.to_string(),
+                        "preview_top" => config.keymaps.preview_top = value.to_string(),
+                        "preview_bottom" => config.keymaps.preview_bottom = value.to_string(),
+                        "next_page" => config.keymaps.next_page = value.to_string(),
+                        "prev_page" => config.keymaps.prev_page = value.to_string(),
+                        "preview_raw" => config.keymaps.preview_raw = value.to_string(),
+                        "toggle_exif" => config.keymaps.toggle_exif = value.to_string(),
+                        "tree_expand" => config.keymaps.tree_expand = value.to_string(),
+                        "tree_collapse" => config.keymaps.tree_collapse = value.to_string(),
+                        "mark" => config.keymaps.mark = value.to_string(),
+                        "extract" => config.keymaps.extract = value.to_string(),
+                        "compress" => config.keymaps.compress = value.to_string(),
+                        "messages" => config.keymaps.messages = value.to_string(),
+                        "choose" => config.keymaps.choose = value.to_string(),
+                        "preview_cache" => {
+                            if let Some(limit) = CacheLimit::parse(value) {
+                                config.preview_cache = limit;
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -103,6 +208,7 @@ impl Default for Config {
         Self {
             keymaps: Keymaps {
                 quit: "q".into(),
+                quit_cd: "Q".into(),
                 create_file: "n".into(),
                 create_folder: "f".into(),
                 rename: "r".into(),
@@ -116,7 +222,26 @@ impl Default for Config {
                 focus: "tab".into(),
                 pin: "u".into(),
                 unpin: "i".into(),
+                preview_down: "J".into(),
+                preview_up: "K".into(),
+                preview_half_down: "D".into(),
+                preview_half_up: "U".into(),
+                preview_top: "g".into(),
+                preview_bottom: "G".into(),
+                next_page: "]".into(),
+                prev_page: "[".into(),
+                preview_raw: "m".into(),
+                toggle_exif: "I".into(),
+                tree_expand: "+".into(),
+                tree_collapse: "-".into(),
+                mark: "space".into(),
+                extract: "e".into(),
+                compress: "z".into(),
+                messages: "L".into(),
+                choose: "C".into(),
             },
+            previewers: Vec::new(),
+            preview_cache: CacheLimit::default(),
         }
     }
 }
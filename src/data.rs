@@ -0,0 +1,1656 @@
+use std::{
+    collections::HashSet,
+    io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use lru::LruCache;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::cache::FileStamp;
+use crate::encoding::{self, Encoding};
+use crate::event::AppEvent;
+use crate::theme::Theme;
+
+// bigger files are previewed as plain text
+pub const MAX_SIZE: u64 = 8 * 1024 * 1024;
+const CACHE_SIZE: usize = 16;
+
+// widest a CSV column gets before its cells are cut short
+const MAX_CELL_WIDTH: usize = 30;
+
+// deeper nesting is almost certainly hostile input
+const MAX_NESTING: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Csv(char),
+}
+
+pub fn format(path: &Path) -> Option<Format> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    match ext.as_str() {
+        "json" => Some(Format::Json),
+        "yaml" | "yml" => Some(Format::Yaml),
+        "toml" => Some(Format::Toml),
+        "csv" => Some(Format::Csv(',')),
+        "tsv" => Some(Format::Csv('\t')),
+        _ => None,
+    }
+}
+
+pub enum Value {
+    Null,
+    Bool(bool),
+    // numbers, dates and other bare scalars, kept as written
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+pub enum Document {
+    Tree(Value),
+    Table(Vec<Vec<String>>),
+}
+
+pub struct ParseError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+pub fn parse(text: &str, format: Format) -> Result<Document, ParseError> {
+    match format {
+        Format::Json => parse_json(text).map(Document::Tree),
+        Format::Yaml => parse_yaml(text).map(Document::Tree),
+        Format::Toml => parse_toml(text).map(Document::Tree),
+        Format::Csv(delimiter) => parse_csv(text, delimiter).map(Document::Table),
+    }
+}
+
+//
+// Cursor shared by the JSON and TOML parsers
+//
+struct Cursor<'a> {
+    src: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0, depth: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        let before = &self.src[..self.pos];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        ParseError {
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(self.error("nesting too deep"));
+        }
+        Ok(())
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(c) => self.error(format!("unexpected {:?}", c)),
+            None => self.error("unexpected end of file"),
+        }
+    }
+
+    /// Double-quoted string with JSON escapes; the cursor is on the opening quote.
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        self.bump();
+        let mut out = String::new();
+
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(out),
+                Some('\\') => out.push(self.escape()?),
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ParseError> {
+        Ok(match self.bump() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('e') => '\u{1b}',
+            Some('u') => {
+                let high = self.hex(4)?;
+
+                // surrogate pair
+                if (0xd800..0xdc00).contains(&high) && self.eat_str("\\u") {
+                    let low = self.hex(4)?;
+                    let c = 0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                    char::from_u32(c).unwrap_or('\u{fffd}')
+                } else {
+                    char::from_u32(high).unwrap_or('\u{fffd}')
+                }
+            }
+            Some('U') => {
+                let c = self.hex(8)?;
+                char::from_u32(c).unwrap_or('\u{fffd}')
+            }
+            _ => return Err(self.error("invalid escape")),
+        })
+    }
+
+    fn hex(&mut self, len: usize) -> Result<u32, ParseError> {
+        let digits = self.rest().get(..len).ok_or_else(|| self.error("invalid unicode escape"))?;
+        let value = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += len;
+        Ok(value)
+    }
+}
+
+//
+// JSON
+//
+fn parse_json(text: &str) -> Result<Value, ParseError> {
+    let mut c = Cursor::new(text.trim_start_matches('\u{feff}'));
+    let value = json_value(&mut c)?;
+
+    c.skip_while(char::is_whitespace);
+    if c.peek().is_some() {
+        return Err(c.error("trailing characters after the document"));
+    }
+
+    Ok(value)
+}
+
+fn json_value(c: &mut Cursor) -> Result<Value, ParseError> {
+    c.skip_while(char::is_whitespace);
+
+    match c.peek() {
+        Some('{') => {
+            c.enter()?;
+            c.bump();
+            let mut fields = Vec::new();
+
+            c.skip_while(char::is_whitespace);
+            if !c.eat('}') {
+                loop {
+                    c.skip_while(char::is_whitespace);
+                    if c.peek() != Some('"') {
+                        return Err(c.error("expected a quoted key"));
+                    }
+                    let key = c.quoted()?;
+
+                    c.skip_while(char::is_whitespace);
+                    if !c.eat(':') {
+                        return Err(c.error("expected ':' after the key"));
+                    }
+
+                    fields.push((key, json_value(c)?));
+
+                    c.skip_while(char::is_whitespace);
+                    if c.eat('}') {
+                        break;
+                    }
+                    if !c.eat(',') {
+                        return Err(c.error("expected ',' or '}'"));
+                    }
+                }
+            }
+
+            c.depth -= 1;
+            Ok(Value::Object(fields))
+        }
+        Some('[') => {
+            c.enter()?;
+            c.bump();
+            let mut items = Vec::new();
+
+            c.skip_while(char::is_whitespace);
+            if !c.eat(']') {
+                loop {
+                    items.push(json_value(c)?);
+
+                    c.skip_while(char::is_whitespace);
+                    if c.eat(']') {
+                        break;
+                    }
+                    if !c.eat(',') {
+                        return Err(c.error("expected ',' or ']'"));
+                    }
+                }
+            }
+
+            c.depth -= 1;
+            Ok(Value::Array(items))
+        }
+        Some('"') => Ok(Value::String(c.quoted()?)),
+        Some('t') if c.eat_str("true") => Ok(Value::Bool(true)),
+        Some('f') if c.eat_str("false") => Ok(Value::Bool(false)),
+        Some('n') if c.eat_str("null") => Ok(Value::Null),
+        Some(ch) if ch == '-' || ch.is_ascii_digit() => {
+            let start = c.pos;
+            let number = c.skip_while(|ch| ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E'));
+
+            if !is_json_number(number) {
+                c.pos = start;
+                return Err(c.error(format!("invalid number {:?}", number)));
+            }
+            Ok(Value::Number(number.to_string()))
+        }
+        _ => Err(c.unexpected()),
+    }
+}
+
+// -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+fn is_json_number(text: &str) -> bool {
+    let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+
+    let s = text.strip_prefix('-').unwrap_or(text);
+    let int = match digits(s) {
+        0 => return false,
+        n if n > 1 && s.starts_with('0') => return false,
+        n => n,
+    };
+
+    let mut rest = &s[int..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let n = digits(fraction);
+        if n == 0 {
+            return false;
+        }
+        rest = &fraction[n..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let n = digits(exponent);
+        if n == 0 {
+            return false;
+        }
+        rest = &exponent[n..];
+    }
+
+    rest.is_empty()
+}
+
+//
+// TOML
+//
+type Table = Vec<(String, Value)>;
+
+fn parse_toml(text: &str) -> Result<Value, ParseError> {
+    let mut c = Cursor::new(text.trim_start_matches('\u{feff}'));
+    let mut root: Table = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+
+    loop {
+        toml_skip_blank(&mut c, true);
+        if c.peek().is_none() {
+            break;
+        }
+
+        if c.eat_str("[[") {
+            // array of tables: append a fresh table to the array at `path`
+            let path = toml_key_path(&mut c)?;
+            if !c.eat_str("]]") {
+                return Err(c.error("expected ']]'"));
+            }
+
+            let (last, parent) = path.split_last().unwrap();
+            let table = descend(&mut root, parent).map_err(|e| c.error(e))?;
+
+            match table.iter_mut().find(|(k, _)| k == last) {
+                Some((_, Value::Array(items))) => items.push(Value::Object(Vec::new())),
+                Some(_) => return Err(c.error(format!("`{}` is already defined", last))),
+                None => table.push((last.clone(), Value::Array(vec![Value::Object(Vec::new())]))),
+            }
+            current = path;
+        } else if c.eat('[') {
+            let path = toml_key_path(&mut c)?;
+            if !c.eat(']') {
+                return Err(c.error("expected ']'"));
+            }
+
+            descend(&mut root, &path).map_err(|e| c.error(e))?;
+            current = path;
+        } else {
+            let start = c.pos;
+            let path = toml_key_path(&mut c)?;
+            if !c.eat('=') {
+                return Err(c.error("expected '=' after the key"));
+            }
+            c.skip_while(|ch| ch == ' ' || ch == '\t');
+
+            let value = toml_value(&mut c)?;
+
+            let (last, parent) = path.split_last().unwrap();
+            let table = descend(&mut root, &current).and_then(|t| descend(t, parent));
+            let table = table.map_err(|e| c.error(e))?;
+
+            if table.iter().any(|(k, _)| k == last) {
+                c.pos = start;
+                return Err(c.error(format!("duplicate key `{}`", last)));
+            }
+            table.push((last.clone(), value));
+        }
+
+        // only a comment may follow on the same line
+        c.skip_while(|ch| ch == ' ' || ch == '\t');
+        if c.peek() == Some('#') {
+            c.skip_while(|ch| ch != '\n');
+        }
+        if !(c.eat('\n') || c.eat_str("\r\n") || c.peek().is_none()) {
+            return Err(c.error("expected a new line"));
+        }
+    }
+
+    Ok(Value::Object(root))
+}
+
+// walk (and create) nested tables; arrays of tables lead into their last element
+fn descend<'t>(mut table: &'t mut Table, path: &[String]) -> Result<&'t mut Table, String> {
+    for key in path {
+        let idx = match table.iter().position(|(k, _)| k == key) {
+            Some(idx) => idx,
+            None => {
+                table.push((key.clone(), Value::Object(Vec::new())));
+                table.len() - 1
+            }
+        };
+
+        table = match &mut table[idx].1 {
+            Value::Object(fields) => fields,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Object(fields)) => fields,
+                _ => return Err(format!("`{}` is not a table", key)),
+            },
+            _ => return Err(format!("`{}` is not a table", key)),
+        };
+    }
+
+    Ok(table)
+}
+
+fn toml_skip_blank(c: &mut Cursor, newlines: bool) {
+    loop {
+        c.skip_while(|ch| ch == ' ' || ch == '\t' || (newlines && (ch == '\n' || ch == '\r')));
+        if c.peek() == Some('#') {
+            c.skip_while(|ch| ch != '\n');
+        } else {
+            break;
+        }
+    }
+}
+
+// `a.b."c d"`
+fn toml_key_path(c: &mut Cursor) -> Result<Vec<String>, ParseError> {
+    let mut path = Vec::new();
+
+    loop {
+        c.skip_while(|ch| ch == ' ' || ch == '\t');
+
+        let key = match c.peek() {
+            Some('"') => c.quoted()?,
+            Some('\'') => toml_literal(c)?,
+            _ => {
+                let key = c.skip_while(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+                if key.is_empty() {
+                    return Err(c.unexpected());
+                }
+                key.to_string()
+            }
+        };
+        path.push(key);
+
+        c.skip_while(|ch| ch == ' ' || ch == '\t');
+        if !c.eat('.') {
+            return Ok(path);
+        }
+    }
+}
+
+fn toml_literal(c: &mut Cursor) -> Result<String, ParseError> {
+    c.bump();
+    let text = c.skip_while(|ch| ch != '\'' && ch != '\n');
+    if !c.eat('\'') {
+        return Err(c.error("unterminated string"));
+    }
+    Ok(text.to_string())
+}
+
+fn toml_value(c: &mut Cursor) -> Result<Value, ParseError> {
+    match c.peek() {
+        Some('"') if c.eat_str("\"\"\"") => {
+            // multi-line basic string; a newline right after the quotes is dropped
+            c.eat_str("\r\n");
+            c.eat('\n');
+            let mut out = String::new();
+
+            loop {
+                if c.eat_str("\"\"\"") {
+                    return Ok(Value::String(out));
+                }
+                match c.bump() {
+                    // line-ending backslash joins lines
+                    Some('\\') if matches!(c.peek(), Some('\n' | '\r' | ' ' | '\t')) => {
+                        c.skip_while(char::is_whitespace);
+                    }
+                    Some('\\') => out.push(c.escape()?),
+                    Some(ch) => out.push(ch),
+                    None => return Err(c.error("unterminated string")),
+                }
+            }
+        }
+        Some('\'') if c.eat_str("'''") => {
+            c.eat_str("\r\n");
+            c.eat('\n');
+            match c.rest().find("'''") {
+                Some(end) => {
+                    let text = c.rest()[..end].to_string();
+                    c.pos += end + 3;
+                    Ok(Value::String(text))
+                }
+                None => Err(c.error("unterminated string")),
+            }
+        }
+        Some('"') => Ok(Value::String(c.quoted()?)),
+        Some('\'') => Ok(Value::String(toml_literal(c)?)),
+        Some('[') => {
+            c.enter()?;
+            c.bump();
+            let mut items = Vec::new();
+
+            loop {
+                toml_skip_blank(c, true);
+                if c.eat(']') {
+                    break;
+                }
+
+                items.push(toml_value(c)?);
+
+                toml_skip_blank(c, true);
+                if c.eat(']') {
+                    break;
+                }
+                if !c.eat(',') {
+                    return Err(c.error("expected ',' or ']'"));
+                }
+            }
+
+            c.depth -= 1;
+            Ok(Value::Array(items))
+        }
+        Some('{') => {
+            c.enter()?;
+            c.bump();
+            let mut table: Table = Vec::new();
+
+            c.skip_while(|ch| ch == ' ' || ch == '\t');
+            if !c.eat('}') {
+                loop {
+                    let path = toml_key_path(c)?;
+                    if !c.eat('=') {
+                        return Err(c.error("expected '=' after the key"));
+                    }
+                    c.skip_while(|ch| ch == ' ' || ch == '\t');
+                    let value = toml_value(c)?;
+
+                    let (last, parent) = path.split_last().unwrap();
+                    let target = descend(&mut table, parent).map_err(|e| c.error(e))?;
+                    target.push((last.clone(), value));
+
+                    c.skip_while(|ch| ch == ' ' || ch == '\t');
+                    if c.eat('}') {
+                        break;
+                    }
+                    if !c.eat(',') {
+                        return Err(c.error("expected ',' or '}'"));
+                    }
+                }
+            }
+
+            c.depth -= 1;
+            Ok(Value::Object(table))
+        }
+        _ => {
+            let start = c.pos;
+            let mut token = c.skip_while(|ch| !matches!(ch, ',' | ']' | '}' | '#' | '\n' | '\r' | ' ' | '\t'));
+
+            // the one bare value allowed a space: "1979-05-27 07:32:00"
+            if is_toml_date(token) && c.peek() == Some(' ') && c.rest()[1..].starts_with(|ch: char| ch.is_ascii_digit()) {
+                c.bump();
+                c.skip_while(|ch| !matches!(ch, ',' | ']' | '}' | '#' | '\n' | '\r' | ' ' | '\t'));
+                token = &c.src[start..c.pos];
+            }
+
+            match token {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ if is_toml_number(token) || is_toml_date(token) => Ok(Value::Number(token.to_string())),
+                "" => Err(c.unexpected()),
+                _ => {
+                    c.pos = start;
+                    Err(c.error(format!("invalid value {:?}", token)))
+                }
+            }
+        }
+    }
+}
+
+fn is_toml_number(token: &str) -> bool {
+    let digits = token.trim_start_matches(['+', '-']);
+    let plain = digits.replace('_', "");
+
+    matches!(digits, "inf" | "nan")
+        || plain.parse::<f64>().is_ok() && plain.starts_with(|c: char| c.is_ascii_digit())
+        || ["0x", "0o", "0b"].iter().any(|p| digits.starts_with(p) && digits.len() > 2)
+}
+
+fn is_toml_date(token: &str) -> bool {
+    let b = token.as_bytes();
+    let date = b.len() >= 10 && b[..4].iter().all(u8::is_ascii_digit) && b[4] == b'-';
+    let time = b.len() >= 8 && b[..2].iter().all(u8::is_ascii_digit) && b[2] == b':';
+    date || time
+}
+
+//
+// YAML (block and flow style, first document only)
+//
+struct Yaml {
+    lines: Vec<String>,
+    pos: usize,
+}
+
+fn parse_yaml(text: &str) -> Result<Value, ParseError> {
+    let mut lines = Vec::new();
+
+    for (n, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+        let trimmed = line.trim();
+
+        if trimmed == "---" || trimmed.starts_with("--- ") || trimmed == "..." {
+            if lines.iter().any(|l: &String| !is_blank(l)) {
+                break;
+            }
+            lines.push(String::new());
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        if line[..indent].contains('\t') {
+            return Err(ParseError {
+                line: n + 1,
+                col: 1,
+                message: "tabs are not allowed in indentation".to_string(),
+            });
+        }
+
+        lines.push(line.to_string());
+    }
+
+    let mut yaml = Yaml { lines, pos: 0 };
+
+    let value = match yaml.peek() {
+        Some((indent, _)) => yaml.block(indent, 0)?,
+        None => Value::Null,
+    };
+
+    if let Some((indent, _)) = yaml.peek() {
+        return Err(yaml.error(indent, "unexpected content after the document"));
+    }
+
+    Ok(value)
+}
+
+fn is_blank(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn is_seq_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+impl Yaml {
+    /// Indent and content of the next line that isn't blank or a comment.
+    fn peek(&mut self) -> Option<(usize, String)> {
+        while self.pos < self.lines.len() && is_blank(&self.lines[self.pos]) {
+            self.pos += 1;
+        }
+
+        let line = self.lines.get(self.pos)?;
+        let content = line.trim_start();
+        Some((line.len() - content.len(), strip_comment(content).to_string()))
+    }
+
+    fn error(&self, indent: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.pos + 1,
+            col: indent + 1,
+            message: message.into(),
+        }
+    }
+
+    // a node whose lines start at `indent`
+    fn block(&mut self, indent: usize, depth: usize) -> Result<Value, ParseError> {
+        if depth > MAX_NESTING {
+            return Err(self.error(indent, "nesting too deep"));
+        }
+
+        let Some((_, text)) = self.peek() else {
+            return Ok(Value::Null);
+        };
+
+        if is_seq_item(&text) {
+            self.sequence(indent, depth)
+        } else if key_split(&text).is_some() {
+            self.mapping(indent, depth)
+        } else {
+            self.pos += 1;
+            let value = yaml_scalar(&text).map_err(|e| self.error(indent, e))?;
+            Ok(self.continue_plain(value, indent))
+        }
+    }
+
+    fn sequence(&mut self, indent: usize, depth: usize) -> Result<Value, ParseError> {
+        let mut items = Vec::new();
+
+        while let Some((at, text)) = self.peek() {
+            if at < indent || (at == indent && !is_seq_item(&text)) {
+                break;
+            }
+            if at > indent {
+                return Err(self.error(at, "bad indentation"));
+            }
+
+            let rest = text[1..].trim_start();
+            let offset = text.len() - rest.len();
+
+            if rest.is_empty() {
+                self.pos += 1;
+                items.push(self.nested(indent, depth)?);
+            } else if is_seq_item(rest) || key_split(rest).is_some() {
+                // `- key: value` opens a mapping indented to where `key` starts
+                self.lines[self.pos] = format!("{}{}", " ".repeat(indent + offset), rest);
+                items.push(self.block(indent + offset, depth + 1)?);
+            } else {
+                self.pos += 1;
+                items.push(self.value(rest, indent, depth)?);
+            }
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    fn mapping(&mut self, indent: usize, depth: usize) -> Result<Value, ParseError> {
+        let mut fields: Vec<(String, Value)> = Vec::new();
+
+        while let Some((at, text)) = self.peek() {
+            if at < indent || (at == indent && is_seq_item(&text)) {
+                break;
+            }
+            if at > indent {
+                return Err(self.error(at, "bad indentation"));
+            }
+
+            let Some((key, rest)) = key_split(&text) else {
+                return Err(self.error(at, "expected `key: value`"));
+            };
+
+            if fields.iter().any(|(k, _)| *k == key) {
+                return Err(self.error(at, format!("duplicate key `{}`", key)));
+            }
+
+            self.pos += 1;
+
+            let value = if rest.is_empty() {
+                // a sequence may sit at the same indent as its key
+                match self.peek() {
+                    Some((next, text)) if next == indent && is_seq_item(&text) => self.sequence(indent, depth + 1)?,
+                    _ => self.nested(indent, depth)?,
+                }
+            } else {
+                self.value(&rest, indent, depth)?
+            };
+
+            fields.push((key, value));
+        }
+
+        Ok(Value::Object(fields))
+    }
+
+    // whatever is indented under the current line, or null
+    fn nested(&mut self, indent: usize, depth: usize) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some((next, _)) if next > indent => self.block(next, depth + 1),
+            _ => Ok(Value::Null),
+        }
+    }
+
+    // inline value after `key:` or `- `
+    fn value(&mut self, text: &str, indent: usize, depth: usize) -> Result<Value, ParseError> {
+        let text = strip_properties(text);
+
+        if text.starts_with('|') || text.starts_with('>') {
+            return Ok(Value::String(self.block_scalar(text, indent)));
+        }
+        if text.is_empty() {
+            return self.nested(indent, depth);
+        }
+
+        let value = yaml_scalar(text).map_err(|e| self.error(indent, e))?;
+        Ok(self.continue_plain(value, indent))
+    }
+
+    // plain scalars may carry on over more-indented lines
+    fn continue_plain(&mut self, value: Value, indent: usize) -> Value {
+        let Value::String(mut s) = value else {
+            return value;
+        };
+
+        while let Some((at, text)) = self.peek() {
+            if at <= indent {
+                break;
+            }
+            s.push(' ');
+            s.push_str(&text);
+            self.pos += 1;
+        }
+
+        Value::String(s)
+    }
+
+    // `|` keeps newlines, `>` folds them; `-` drops the final one
+    fn block_scalar(&mut self, header: &str, indent: usize) -> String {
+        let literal = header.starts_with('|');
+        let mut body: Vec<&str> = Vec::new();
+        let mut block_indent = None;
+
+        while let Some(line) = self.lines.get(self.pos) {
+            let content = line.trim_start();
+            let at = line.len() - content.len();
+
+            if content.is_empty() {
+                body.push("");
+                self.pos += 1;
+                continue;
+            }
+            if at <= indent {
+                break;
+            }
+
+            let block = *block_indent.get_or_insert(at);
+            body.push(&line[block.min(at)..]);
+            self.pos += 1;
+        }
+
+        while body.last() == Some(&"") {
+            body.pop();
+        }
+
+        let mut text = if literal {
+            body.join("\n")
+        } else {
+            body.iter()
+                .map(|l| if l.is_empty() { "\n" } else { l })
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace(" \n ", "\n")
+        };
+
+        if !header.contains('-') {
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// a ` #` outside quotes starts a comment
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if prev == ' ' || prev == '\t' => return text[..i].trim_end(),
+            _ => {}
+        }
+        prev = c;
+    }
+
+    text.trim_end()
+}
+
+// anchors and tags don't change what's displayed
+fn strip_properties(text: &str) -> &str {
+    let mut text = text.trim();
+
+    while text.starts_with('&') || text.starts_with('!') {
+        text = text.split_once(' ').map(|(_, rest)| rest.trim_start()).unwrap_or("");
+    }
+
+    text
+}
+
+// `key: value`, `key:`, `"quoted key": value`
+fn key_split(text: &str) -> Option<(String, String)> {
+    if text.starts_with('[') || text.starts_with('{') || is_seq_item(text) {
+        return None;
+    }
+
+    let (key, rest) = match text.chars().next()? {
+        q @ ('"' | '\'') => {
+            let end = text[1..].find(q)? + 1;
+            let rest = text[end + 1..].strip_prefix(':')?;
+            (text[1..end].to_string(), rest)
+        }
+        _ => {
+            let colon = text
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| text[i + 1..].is_empty() || text[i + 1..].starts_with([' ', '\t']))?;
+            (text[..colon].trim_end().to_string(), &text[colon + 1..])
+        }
+    };
+
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    Some((key, rest.trim().to_string()))
+}
+
+fn yaml_scalar(text: &str) -> Result<Value, String> {
+    let text = strip_properties(text);
+
+    match text.chars().next() {
+        Some('"') => {
+            let mut c = Cursor::new(text);
+            let s = c.quoted().map_err(|e| e.message)?;
+            if !c.rest().trim().is_empty() {
+                return Err("unexpected text after the string".to_string());
+            }
+            Ok(Value::String(s))
+        }
+        Some('\'') => {
+            let inner = text[1..]
+                .strip_suffix('\'')
+                .ok_or_else(|| "unterminated string".to_string())?;
+            Ok(Value::String(inner.replace("''", "'")))
+        }
+        Some('[' | '{') => {
+            let mut c = Cursor::new(text);
+            let value = flow_value(&mut c).map_err(|e| e.message)?;
+            if !c.rest().trim().is_empty() {
+                return Err("unexpected text after the collection".to_string());
+            }
+            Ok(value)
+        }
+        _ => Ok(plain_scalar(text)),
+    }
+}
+
+fn plain_scalar(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ if text.parse::<f64>().is_ok() && !text.ends_with('.') => Value::Number(text.to_string()),
+        _ if text.starts_with("0x") && i64::from_str_radix(&text[2..], 16).is_ok() => Value::Number(text.to_string()),
+        _ => Value::String(text.to_string()),
+    }
+}
+
+// `[a, b]` and `{k: v}` with unquoted scalars
+fn flow_value(c: &mut Cursor) -> Result<Value, ParseError> {
+    c.skip_while(char::is_whitespace);
+
+    match c.peek() {
+        Some('[') => {
+            c.enter()?;
+            c.bump();
+            let mut items = Vec::new();
+
+            loop {
+                c.skip_while(char::is_whitespace);
+                if c.eat(']') {
+                    break;
+                }
+                items.push(flow_value(c)?);
+                c.skip_while(char::is_whitespace);
+                if c.eat(']') {
+                    break;
+                }
+                if !c.eat(',') {
+                    return Err(c.error("expected ',' or ']'"));
+                }
+            }
+
+            c.depth -= 1;
+            Ok(Value::Array(items))
+        }
+        Some('{') => {
+            c.enter()?;
+            c.bump();
+            let mut fields = Vec::new();
+
+            loop {
+                c.skip_while(char::is_whitespace);
+                if c.eat('}') {
+                    break;
+                }
+
+                let key = match c.peek() {
+                    Some('"') => c.quoted()?,
+                    _ => c.skip_while(|ch| !matches!(ch, ':' | ',' | '}')).trim().to_string(),
+                };
+
+                c.skip_while(char::is_whitespace);
+                let value = if c.eat(':') { flow_value(c)? } else { Value::Null };
+                fields.push((key, value));
+
+                c.skip_while(char::is_whitespace);
+                if c.eat('}') {
+                    break;
+                }
+                if !c.eat(',') {
+                    return Err(c.error("expected ',' or '}'"));
+                }
+            }
+
+            c.depth -= 1;
+            Ok(Value::Object(fields))
+        }
+        Some('"') => Ok(Value::String(c.quoted()?)),
+        Some('\'') => {
+            c.bump();
+            let s = c.skip_while(|ch| ch != '\'').to_string();
+            if !c.eat('\'') {
+                return Err(c.error("unterminated string"));
+            }
+            Ok(Value::String(s))
+        }
+        _ => Ok(plain_scalar(c.skip_while(|ch| !matches!(ch, ',' | ']' | '}')).trim())),
+    }
+}
+
+//
+// CSV / TSV
+//
+fn parse_csv(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, ParseError> {
+    let mut c = Cursor::new(text.trim_start_matches('\u{feff}'));
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+
+    loop {
+        match c.peek() {
+            // quoted field; "" is a literal quote
+            Some('"') if field.is_empty() => {
+                c.bump();
+                loop {
+                    match c.bump() {
+                        Some('"') if c.eat('"') => field.push('"'),
+                        Some('"') => break,
+                        Some(ch) => field.push(ch),
+                        None => return Err(c.error("unterminated quoted field")),
+                    }
+                }
+            }
+            Some(ch) if ch == delimiter => {
+                c.bump();
+                row.push(std::mem::take(&mut field));
+            }
+            Some('\r') => {
+                c.bump();
+            }
+            Some('\n') | None => {
+                let end = c.bump().is_none();
+                if !(row.is_empty() && field.is_empty()) {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                if end {
+                    break;
+                }
+            }
+            Some(ch) => {
+                c.bump();
+                field.push(ch);
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+//
+// Background rendering
+//
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct DataKey {
+    pub path: PathBuf,
+    pub stamp: FileStamp,
+    // only trees fold, so tables always use 0
+    pub depth: usize,
+    pub width: u16,
+}
+
+type Rendered = Result<Arc<Vec<Line<'static>>>, String>;
+
+/// Parsed and laid out data files by version, depth and width, worked out off the draw path.
+pub struct DataPreviews {
+    cache: LruCache<DataKey, Rendered>,
+    pending: HashSet<DataKey>,
+    tx: Sender<(DataKey, Rendered)>,
+    rx: Receiver<(DataKey, Rendered)>,
+    waker: Sender<AppEvent>,
+}
+
+impl DataPreviews {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            pending: HashSet::new(),
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    /// Rendered lines for `key`, or `None` while they're worked out in the background.
+    pub fn request(&mut self, key: &DataKey, format: Format, encoding: Encoding, theme: &Theme) -> Option<Rendered> {
+        while let Ok((done, lines)) = self.rx.try_recv() {
+            self.pending.remove(&done);
+            self.cache.put(done, lines);
+        }
+
+        if let Some(lines) = self.cache.get(key) {
+            return Some(lines.clone());
+        }
+
+        if self.pending.insert(key.clone()) {
+            let key = key.clone();
+            let theme = theme.clone();
+            let tx = self.tx.clone();
+            let waker = self.waker.clone();
+
+            thread::spawn(move || {
+                let lines = render(&key, format, encoding, &theme)
+                    .map(Arc::new)
+                    .map_err(|e| e.to_string());
+                let _ = tx.send((key, lines));
+                let _ = waker.send(AppEvent::Worker);
+            });
+        }
+
+        None
+    }
+
+    pub fn invalidate(&mut self, path: &Path) {
+        let stale: Vec<DataKey> = self.cache.iter().map(|(k, _)| k).filter(|k| k.path == path).cloned().collect();
+
+        for key in stale {
+            self.cache.pop(&key);
+        }
+    }
+}
+
+// read, parse and lay out the whole file; a broken one shows its source with the error
+fn render(key: &DataKey, format: Format, encoding: Encoding, theme: &Theme) -> io::Result<Vec<Line<'static>>> {
+    let text = encoding::read(&key.path, encoding)?;
+
+    let lines = match parse(&text, format) {
+        Ok(Document::Tree(value)) => render_tree(&value, key.depth, theme),
+        Ok(Document::Table(rows)) => render_table(&rows, theme),
+        Err(err) => render_error(&text, &err, theme),
+    };
+
+    Ok(lines.into_iter().map(|line| clip(line, key.width as usize)).collect())
+}
+
+// the preview doesn't wrap, so nothing past `width` columns would show
+fn clip(line: Line<'static>, width: usize) -> Line<'static> {
+    if line.width() <= width {
+        return line;
+    }
+
+    let mut used = 0;
+    let mut spans = Vec::new();
+
+    for span in line.spans {
+        let span_width = span.content.width();
+        if used + span_width <= width {
+            used += span_width;
+            spans.push(span);
+            continue;
+        }
+
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w > width {
+                break;
+            }
+            text.push(c);
+            used += w;
+        }
+        spans.push(Span::styled(text, span.style));
+        break;
+    }
+
+    Line::from(spans)
+}
+
+//
+// Rendering
+//
+/// Pretty-printed tree; containers deeper than `depth` are folded to a summary.
+pub fn render_tree(value: &Value, depth: usize, theme: &Theme) -> Vec<Line<'static>> {
+    let mut out = Vec::new();
+    tree_lines(&mut out, None, value, 0, depth, true, theme);
+    out
+}
+
+fn tree_lines(
+    out: &mut Vec<Line<'static>>,
+    key: Option<&str>,
+    value: &Value,
+    indent: usize,
+    depth: usize,
+    last: bool,
+    theme: &Theme,
+) {
+    let punct = Style::default().fg(theme.muted);
+    let comma = if last { "" } else { "," };
+
+    let mut spans = vec![Span::raw("  ".repeat(indent))];
+    if let Some(key) = key {
+        spans.push(Span::styled(format!("{:?}", key), Style::default().fg(theme.directory)));
+        spans.push(Span::styled(": ", punct));
+    }
+
+    let (open, close, children): (&str, &str, Vec<(Option<&str>, &Value)>) = match value {
+        Value::Object(fields) => ("{", "}", fields.iter().map(|(k, v)| (Some(k.as_str()), v)).collect()),
+        Value::Array(items) => ("[", "]", items.iter().map(|v| (None, v)).collect()),
+        scalar => {
+            spans.push(scalar_span(scalar, theme));
+            spans.push(Span::styled(comma, punct));
+            out.push(Line::from(spans));
+            return;
+        }
+    };
+
+    if children.is_empty() {
+        spans.push(Span::styled(format!("{}{}{}", open, close, comma), punct));
+        out.push(Line::from(spans));
+        return;
+    }
+
+    if depth == 0 {
+        let noun = match (value, children.len()) {
+            (Value::Object(_), 1) => "key",
+            (Value::Object(_), _) => "keys",
+            (_, 1) => "item",
+            _ => "items",
+        };
+        spans.push(Span::styled(format!("{}…{}{}", open, close, comma), punct));
+        spans.push(Span::styled(
+            format!(" {} {}", children.len(), noun),
+            Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC),
+        ));
+        out.push(Line::from(spans));
+        return;
+    }
+
+    spans.push(Span::styled(open, punct));
+    out.push(Line::from(spans));
+
+    let count = children.len();
+    for (i, (key, child)) in children.into_iter().enumerate() {
+        tree_lines(out, key, child, indent + 1, depth - 1, i + 1 == count, theme);
+    }
+
+    out.push(Line::from(vec![
+        Span::raw("  ".repeat(indent)),
+        Span::styled(format!("{}{}", close, comma), punct),
+    ]));
+}
+
+fn scalar_span(value: &Value, theme: &Theme) -> Span<'static> {
+    match value {
+        Value::Null => Span::styled("null", Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC)),
+        Value::Bool(b) => Span::styled(b.to_string(), Style::default().fg(theme.focus_border)),
+        Value::Number(n) => Span::styled(n.clone(), Style::default().fg(theme.focus_border)),
+        Value::String(s) => Span::styled(format!("{:?}", s), Style::default().fg(theme.status_fg)),
+        Value::Array(_) | Value::Object(_) => Span::raw(""),
+    }
+}
+
+/// Column-aligned table with the first row as the header.
+pub fn render_table(rows: &[Vec<String>], theme: &Theme) -> Vec<Line<'static>> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| {
+            rows.iter()
+                .filter_map(|r| r.get(c))
+                .map(|s| cell_text(s).width().min(MAX_CELL_WIDTH))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let border = Style::default().fg(theme.border);
+    let mut out = Vec::new();
+
+    for (n, row) in rows.iter().enumerate() {
+        let style = if n == 0 {
+            Style::default().fg(theme.directory).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.foreground)
+        };
+
+        let mut spans = Vec::new();
+        for (c, &width) in widths.iter().enumerate() {
+            if c > 0 {
+                spans.push(Span::styled(" │ ", border));
+            }
+            let cell = truncate(&cell_text(row.get(c).map(String::as_str).unwrap_or("")), width);
+            let pad = width - cell.width();
+            spans.push(Span::styled(format!("{}{}", cell, " ".repeat(pad)), style));
+        }
+        out.push(Line::from(spans));
+
+        if n == 0 {
+            let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+            out.push(Line::from(Span::styled(rule.join("─┼─"), border)));
+        }
+    }
+
+    out
+}
+
+// keep every row on one line
+fn cell_text(cell: &str) -> String {
+    cell.replace('\n', "⏎").replace('\t', " ")
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+
+    out.push('…');
+    out
+}
+
+/// The source with the failing line marked, so a broken file still shows something useful.
+pub fn render_error(text: &str, err: &ParseError, theme: &Theme) -> Vec<Line<'static>> {
+    let error = Style::default().fg(theme.error).add_modifier(Modifier::BOLD);
+    let muted = Style::default().fg(theme.muted);
+
+    let lines: Vec<&str> = text.lines().collect();
+    let number_width = lines.len().max(1).to_string().len();
+
+    let mut out = vec![
+        Line::from(Span::styled(
+            format!("✗ line {}, column {}: {}", err.line, err.col, err.message),
+            error,
+        )),
+        Line::from(""),
+    ];
+
+    for (i, line) in lines.iter().enumerate() {
+        let number = i + 1;
+
+        if number == err.line {
+            out.push(Line::from(vec![
+                Span::styled(format!("▶ {:>width$} │ ", number, width = number_width), error),
+                Span::styled(line.to_string(), error),
+            ]));
+
+            let caret_at: usize = line.chars().take(err.col.saturating_sub(1)).map(|c| c.width().unwrap_or(0)).sum();
+            out.push(Line::from(vec![
+                Span::styled(format!("  {:>width$} │ ", "", width = number_width), muted),
+                Span::styled(format!("{}^", " ".repeat(caret_at)), error),
+            ]));
+        } else {
+            out.push(Line::from(vec![
+                Span::styled(format!("  {:>width$} │ ", number, width = number_width), muted),
+                Span::raw(line.to_string()),
+            ]));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // compact JSON-like form, for comparing whole documents
+    fn flat(value: &Value) -> String {
+        match value {
+            Value::Null => "null".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.clone(),
+            Value::String(s) => format!("{:?}", s),
+            Value::Array(items) => format!("[{}]", items.iter().map(flat).collect::<Vec<_>>().join(",")),
+            Value::Object(fields) => format!(
+                "{{{}}}",
+                fields.iter().map(|(k, v)| format!("{}:{}", k, flat(v))).collect::<Vec<_>>().join(",")
+            ),
+        }
+    }
+
+    fn tree(text: &str, format: Format) -> String {
+        match parse(text, format) {
+            Ok(Document::Tree(value)) => flat(&value),
+            Ok(Document::Table(_)) => panic!("expected a tree"),
+            Err(e) => panic!("{}:{}: {}", e.line, e.col, e.message),
+        }
+    }
+
+    fn table(text: &str, delimiter: char) -> Vec<Vec<String>> {
+        match parse(text, Format::Csv(delimiter)) {
+            Ok(Document::Table(rows)) => rows,
+            Ok(Document::Tree(_)) => panic!("expected a table"),
+            Err(e) => panic!("{}:{}: {}", e.line, e.col, e.message),
+        }
+    }
+
+    fn error(text: &str, format: Format) -> ParseError {
+        match parse(text, format) {
+            Err(e) => e,
+            Ok(_) => panic!("{:?} should not parse", text),
+        }
+    }
+
+    #[test]
+    fn json_documents() {
+        let text = "\u{feff}{ \"a\": [1, -2.5, 3e2, true, false, null],\n  \"b\": {\"c\": \"x\\ty\\u00e9\\ud83d\\ude00\"}, \"d\": {} }";
+        assert_eq!(tree(text, Format::Json), r#"{a:[1,-2.5,3e2,true,false,null],b:{c:"x\tyé😀"},d:{}}"#);
+        assert_eq!(tree(" \"only\" ", Format::Json), r#""only""#);
+    }
+
+    #[test]
+    fn json_numbers_follow_the_grammar() {
+        for good in ["0", "-0", "10", "0.5", "-1.25e-3", "2E+10", "1e5"] {
+            assert_eq!(tree(good, Format::Json), good);
+        }
+        for bad in ["01", "-01", "00", "-", "1.", ".5", "1e", "1e+", "1.e3", "1-2", "--1", "0x10"] {
+            assert!(parse(bad, Format::Json).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn json_errors_point_at_the_problem() {
+        let e = error("{\n  \"a\": 1,\n  \"b\" 2\n}", Format::Json);
+        assert_eq!((e.line, e.col), (3, 7));
+        assert!(e.message.contains("':'"));
+
+        let e = error("[1, 2] x", Format::Json);
+        assert_eq!((e.line, e.col), (1, 8));
+
+        assert!(error("\"open", Format::Json).message.contains("unterminated"));
+        assert!(error(&"[".repeat(MAX_NESTING + 1), Format::Json).message.contains("too deep"));
+        assert!(error("{a: 1}", Format::Json).message.contains("quoted key"));
+    }
+
+    #[test]
+    fn yaml_documents() {
+        let text = "\
+---
+# settings
+name: fren   # trailing comment
+count: 3
+ratio: 0.5
+on: true
+none: ~
+tags: [a, \"b c\", 1]
+inline: {x: 1, y: two}
+list:
+- plain
+- key: value
+  other: 2
+- - nested
+nested:
+  deep:
+    deeper: 'it''s'
+literal: |
+  line one
+  line two
+folded: >-
+  folded
+  text
+anchored: &anchor value
+";
+        assert_eq!(
+            tree(text, Format::Yaml),
+            "{name:\"fren\",count:3,ratio:0.5,on:true,none:null,tags:[\"a\",\"b c\",1],inline:{x:1,y:\"two\"},\
+             list:[\"plain\",{key:\"value\",other:2},[\"nested\"]],nested:{deep:{deeper:\"it's\"}},\
+             literal:\"line one\\nline two\\n\",folded:\"folded text\",anchored:\"value\"}"
+        );
+    }
+
+    #[test]
+    fn yaml_reads_only_the_first_document() {
+        assert_eq!(tree("a: 1\n---\nb: 2\n", Format::Yaml), "{a:1}");
+        assert_eq!(tree("", Format::Yaml), "null");
+        assert_eq!(tree("- 1\n- 2\n", Format::Yaml), "[1,2]");
+    }
+
+    #[test]
+    fn yaml_errors() {
+        let e = error("a: 1\na: 2\n", Format::Yaml);
+        assert_eq!(e.line, 2);
+        assert!(e.message.contains("duplicate"));
+
+        let e = error("a:\n\tb: 1\n", Format::Yaml);
+        assert_eq!(e.line, 2);
+        assert!(e.message.contains("tabs"));
+
+        assert!(error("a: 1\n   b: 2\n", Format::Yaml).message.contains("indentation"));
+        assert!(error("a: \"open\n", Format::Yaml).message.contains("unterminated"));
+    }
+
+    #[test]
+    fn toml_documents() {
+        let text = r#"
+# top
+title = "fren" # comment
+count = 1_000
+hex = 0xff
+pi = 3.14
+when = 1979-05-27 07:32:00
+site.name = 'literal \n'
+
+[server]
+ports = [ 80,
+  443, ]
+point = { x = 1, y.z = 2 }
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = """
+multi
+line"""
+"#;
+        assert_eq!(
+            tree(text, Format::Toml),
+            "{title:\"fren\",count:1_000,hex:0xff,pi:3.14,when:1979-05-27 07:32:00,site:{name:\"literal \\\\n\"},\
+             server:{ports:[80,443],point:{x:1,y:{z:2}}},bin:[{name:\"a\"},{name:\"multi\\nline\"}]}"
+        );
+    }
+
+    #[test]
+    fn toml_errors() {
+        let e = error("a = 1\na = 2\n", Format::Toml);
+        assert_eq!((e.line, e.col), (2, 1));
+        assert!(e.message.contains("duplicate"));
+
+        assert!(error("a = nope\n", Format::Toml).message.contains("invalid value"));
+        assert!(error("a = 1 b = 2\n", Format::Toml).message.contains("new line"));
+        assert!(error("a = 1\n[a]\n", Format::Toml).message.contains("not a table"));
+        assert!(error("[table\n", Format::Toml).message.contains("']'"));
+        assert!(error("a = \"\"\"open\n", Format::Toml).message.contains("unterminated"));
+    }
+
+    #[test]
+    fn csv_tables() {
+        let text = "\u{feff}name,note\r\n\"Doe, Jane\",\"said \"\"hi\"\"\"\r\nbob,\"two\nlines\"\n\nlast,\n";
+        assert_eq!(
+            table(text, ','),
+            [
+                vec!["name", "note"],
+                vec!["Doe, Jane", "said \"hi\""],
+                vec!["bob", "two\nlines"],
+                vec!["last", ""],
+            ]
+        );
+
+        assert_eq!(table("a\tb,c\n1\t2", '\t'), [vec!["a", "b,c"], vec!["1", "2"]]);
+        assert!(error("a,\"open\n", Format::Csv(',')).message.contains("unterminated"));
+    }
+
+    #[test]
+    fn trees_fold_below_the_depth() {
+        let Ok(Document::Tree(value)) = parse(r#"{"a": {"b": [1, 2]}, "c": []}"#, Format::Json) else {
+            panic!("expected a tree");
+        };
+        let text = |depth| -> Vec<String> { render_tree(&value, depth, &Theme::default()).iter().map(Line::to_string).collect() };
+
+        assert_eq!(text(0), ["{…} 2 keys"]);
+        assert_eq!(text(1), ["{", "  \"a\": {…}, 1 key", "  \"c\": []", "}"]);
+        assert_eq!(text(3).len(), 9);
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_width() {
+        let line = Line::from(vec![Span::raw("abc"), Span::raw("défg"), Span::raw("hi")]);
+        assert_eq!(clip(line.clone(), 5).to_string(), "abcdé");
+        assert_eq!(clip(line.clone(), 3).to_string(), "abc");
+        assert_eq!(clip(line.clone(), 20).to_string(), "abcdéfghi");
+
+        // a wide character that doesn't fit stays out
+        assert_eq!(clip(Line::from("a漢字"), 4).to_string(), "a漢");
+    }
+
+    #[test]
+    fn previews_are_rendered_in_the_background() {
+        let path = std::env::temp_dir().join(format!("fren-data-{}-preview.json", std::process::id()));
+        std::fs::write(&path, r#"{"a": {"b": 1}}"#).unwrap();
+
+        let (waker, woken) = mpsc::channel();
+        let mut previews = DataPreviews::new(waker);
+        let theme = Theme::default();
+        let key = DataKey {
+            path: path.clone(),
+            stamp: FileStamp::of(&path),
+            depth: 1,
+            width: 80,
+        };
+
+        assert!(previews.request(&key, Format::Json, Encoding::Utf8, &theme).is_none());
+        woken.recv().unwrap();
+        let lines = previews.request(&key, Format::Json, Encoding::Utf8, &theme).unwrap().unwrap();
+        assert_eq!(lines.len(), 3);
+
+        // a broken file shows its source under the error
+        std::fs::write(&path, "{\"a\": }").unwrap();
+        let key = DataKey { stamp: FileStamp::of(&path), ..key };
+        previews.invalidate(&path);
+        assert!(previews.request(&key, Format::Json, Encoding::Utf8, &theme).is_none());
+        woken.recv().unwrap();
+        let lines = previews.request(&key, Format::Json, Encoding::Utf8, &theme).unwrap().unwrap();
+        assert!(lines[0].to_string().starts_with("✗ line 1, column 7"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
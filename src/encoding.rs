@@ -0,0 +1,247 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+// enough to judge the encoding and line endings without reading the whole file
+const SAMPLE: usize = 8 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 (BOM)",
+            Self::Utf16Le => "UTF-16 LE",
+            Self::Utf16Be => "UTF-16 BE",
+            Self::ShiftJis => "Shift-JIS",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+    Mixed,
+    None,
+}
+
+impl LineEnding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+            Self::Cr => "CR",
+            Self::Mixed => "Mixed",
+            Self::None => "-",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TextInfo {
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+}
+
+/// Sniff the start of a file. `None` means it looks binary.
+pub fn sniff(path: &Path) -> Option<TextInfo> {
+    let mut sample = Vec::with_capacity(SAMPLE);
+    File::open(path).ok()?.take(SAMPLE as u64).read_to_end(&mut sample).ok()?;
+
+    let encoding = detect(&sample)?;
+
+    // only UTF-16 moves CR and LF away from their ASCII bytes
+    let text = match encoding {
+        Encoding::Utf16Le | Encoding::Utf16Be => decode(&sample, encoding),
+        _ => String::from_utf8_lossy(&sample).into_owned(),
+    };
+
+    Some(TextInfo {
+        encoding,
+        line_ending: line_ending(&text),
+    })
+}
+
+/// BOMs first, then UTF-16 (whose NULs would otherwise look binary),
+/// UTF-8, Shift-JIS and finally Latin-1 for anything that isn't mostly control bytes.
+pub fn detect(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+        return Some(Encoding::Utf8Bom);
+    }
+    if bytes.starts_with(&[0xff, 0xfe]) {
+        return Some(Encoding::Utf16Le);
+    }
+    if bytes.starts_with(&[0xfe, 0xff]) {
+        return Some(Encoding::Utf16Be);
+    }
+
+    if let Some(encoding) = detect_utf16(bytes) {
+        return Some(encoding);
+    }
+    if bytes.contains(&0) {
+        return None;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => return Some(Encoding::Utf8),
+        // the sample may end part way through a character
+        Err(e) if e.error_len().is_none() => return Some(Encoding::Utf8),
+        Err(_) => {}
+    }
+
+    if is_shift_jis(bytes) {
+        return Some(Encoding::ShiftJis);
+    }
+
+    let controls = bytes
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+
+    (controls * 50 <= bytes.len()).then_some(Encoding::Latin1)
+}
+
+// mostly-ASCII UTF-16 has a NUL in every other byte
+fn detect_utf16(bytes: &[u8]) -> Option<Encoding> {
+    let pairs = bytes.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+
+    let even = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    if odd * 10 >= pairs * 4 && even * 10 < pairs {
+        Some(Encoding::Utf16Le)
+    } else if even * 10 >= pairs * 4 && odd * 10 < pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+// every high byte must start a valid lead/trail pair or be half-width katakana
+fn is_shift_jis(bytes: &[u8]) -> bool {
+    let mut pairs = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            0x00..=0x7f | 0xa1..=0xdf => i += 1,
+            0x81..=0x9f | 0xe0..=0xfc => match bytes.get(i + 1) {
+                Some(0x40..=0x7e | 0x80..=0xfc) => {
+                    pairs += 1;
+                    i += 2;
+                }
+                // cut off by the end of the sample
+                None => break,
+                Some(_) => return false,
+            },
+            _ => return false,
+        }
+    }
+
+    pairs > 0
+}
+
+/// Read a whole file as text in the given encoding.
+pub fn read(path: &Path, encoding: Encoding) -> io::Result<String> {
+    Ok(decode(&fs::read(path)?, encoding))
+}
+
+pub fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        Encoding::Utf8Bom => String::from_utf8_lossy(bytes.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(bytes)).into_owned(),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = bytes.chunks_exact(2).map(|b| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([b[0], b[1]]),
+                _ => u16::from_be_bytes([b[0], b[1]]),
+            });
+
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect::<String>()
+                .trim_start_matches('\u{feff}')
+                .to_string()
+        }
+        Encoding::ShiftJis => iconv(bytes, "SHIFT_JIS").unwrap_or_else(|| decode(bytes, Encoding::Latin1)),
+        Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+// no decoding tables here, iconv has them
+fn iconv(bytes: &[u8], from: &str) -> Option<String> {
+    let mut child = Command::new("iconv")
+        .args(["-c", "-f", from, "-t", "UTF-8"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // feed stdin from another thread so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take()?;
+    let input = bytes.to_vec();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output().ok()?;
+    let _ = writer.join();
+
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let cr = text.matches('\r').count() - crlf;
+
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => LineEnding::None,
+        (true, false, false) => LineEnding::Lf,
+        (false, true, false) => LineEnding::Crlf,
+        (false, false, true) => LineEnding::Cr,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Swap control characters for their visible symbols (␛, ␀, ...) and expand tabs,
+/// so stray bytes can't move the cursor or break the layout.
+pub fn show_controls(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut col = 0;
+
+    for c in line.chars() {
+        match c {
+            '\t' => {
+                let pad = 4 - col % 4;
+                out.extend(std::iter::repeat_n(' ', pad));
+                col += pad;
+                continue;
+            }
+            '\0'..='\u{1f}' => out.push(char::from_u32(0x2400 + c as u32).unwrap_or('�')),
+            '\u{7f}' => out.push('␡'),
+            '\u{80}'..='\u{9f}' => out.push('�'),
+            _ => out.push(c),
+        }
+        col += 1;
+    }
+
+    out
+}
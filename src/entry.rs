@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    fs::{self, DirEntry},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::cache::FileStamp;
+
+/// Rough type of a file, from its extension, for icons and the like.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Directory,
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Rust,
+    C,
+    Python,
+    JavaScript,
+    TypeScript,
+    Config,
+    Other,
+}
+
+impl FileKind {
+    pub fn detect(path: &Path, is_dir: bool) -> Self {
+        if is_dir {
+            return Self::Directory;
+        }
+
+        match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
+            "png" | "jpg" | "jpeg" | "webp" | "gif" => Self::Image,
+            "mp3" | "wav" | "flac" => Self::Audio,
+            "mp4" | "mkv" | "mov" => Self::Video,
+            "zip" | "tar" | "gz" | "tgz" | "xz" | "zst" | "rar" => Self::Archive,
+            "rs" => Self::Rust,
+            "c" | "cpp" | "h" => Self::C,
+            "py" => Self::Python,
+            "js" => Self::JavaScript,
+            "ts" => Self::TypeScript,
+            "toml" | "json" | "yaml" | "yml" => Self::Config,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// One row of the file list, with everything the list, sorting and the
+/// Metadata panel need read once when the directory is loaded.
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    /// Follows symlinks, like `Path::is_dir`
+    pub is_dir: bool,
+    pub is_file: bool,
+    /// Where the entry points when it is a symlink
+    pub link_target: Option<PathBuf>,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub mode: u32,
+    pub owner: String,
+    pub kind: FileKind,
+}
+
+impl Entry {
+    pub fn new(entry: &DirEntry, owners: &HashMap<u32, String>) -> Self {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        let link = fs::symlink_metadata(&path).ok();
+        let link_target = link
+            .as_ref()
+            .filter(|m| m.file_type().is_symlink())
+            .and_then(|_| fs::read_link(&path).ok());
+
+        // a broken link still has its own metadata
+        let meta = fs::metadata(&path).ok().or(link);
+
+        let is_dir = meta.as_ref().is_some_and(|m| m.is_dir());
+        let is_file = meta.as_ref().is_some_and(|m| m.is_file());
+        let uid = meta.as_ref().map(|m| m.uid());
+
+        Self {
+            kind: FileKind::detect(&path, is_dir),
+            size: meta.as_ref().map(|m| m.len()).unwrap_or(0),
+            modified: meta.as_ref().and_then(|m| m.modified().ok()),
+            mode: meta.as_ref().map(|m| m.permissions().mode()).unwrap_or(0),
+            owner: match uid {
+                Some(uid) => owners.get(&uid).cloned().unwrap_or_else(|| uid.to_string()),
+                None => "-".to_string(),
+            },
+            name,
+            path,
+            is_dir,
+            is_file,
+            link_target,
+        }
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.link_target.is_some()
+    }
+
+    /// mtime and size as read for the listing, for cache keys.
+    pub fn stamp(&self) -> FileStamp {
+        FileStamp {
+            modified: self.modified,
+            size: self.size,
+        }
+    }
+}
+
+/// User names by uid, from /etc/passwd.
+pub fn owners() -> HashMap<u32, String> {
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
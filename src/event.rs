@@ -1,20 +1,78 @@
-use std::io;
+use std::{io, sync::mpsc::Sender, thread};
 
-use crossterm::event::{self, Event, KeyCode};
-use ratatui::{Terminal, backend::CrosstermBackend};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Position};
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP};
 
 use crate::app::{App, AppMode, Focus, InputAction};
+use crate::archive::ConflictPolicy;
 use crate::config::Config;
 use crate::theme::Theme;
 
+//
+// Event channel
+//
+/// Everything that wakes the main loop. Results themselves stay on each
+/// worker's own channel; `Worker` just says one of them has something.
+pub enum AppEvent {
+    Input(Event),
+    Worker,
+    Signal(i32),
+    /// A worker thread panicked; what it said
+    Panic(String),
+}
+
+impl AppEvent {
+    // the pointer moving over the window changes nothing on screen
+    pub fn redraws(&self) -> bool {
+        !matches!(self, AppEvent::Input(Event::Mouse(MouseEvent { kind: MouseEventKind::Moved, .. })))
+    }
+
+    pub fn quits(&self) -> bool {
+        matches!(self, AppEvent::Signal(SIGTERM | SIGINT | SIGHUP))
+    }
+
+    // raw mode turns Ctrl-Z into a key press, so it never raises SIGTSTP itself
+    pub fn suspends(&self) -> bool {
+        match self {
+            AppEvent::Input(Event::Key(KeyEvent { code: KeyCode::Char('z'), modifiers, .. })) => {
+                modifiers.contains(KeyModifiers::CONTROL)
+            }
+            AppEvent::Signal(signal) => *signal == SIGTSTP,
+            _ => false,
+        }
+    }
+
+    pub fn resumes(&self) -> bool {
+        matches!(self, AppEvent::Signal(SIGCONT))
+    }
+}
+
+/// Read the terminal on its own thread, so the main loop only waits on one channel.
+pub fn spawn_input(tx: Sender<AppEvent>) {
+    thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if tx.send(AppEvent::Input(event)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
 pub fn handle_events(
     app: &mut App,
+    event: Event,
     _terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     config: &Config,
     _theme: &Theme,
 ) -> io::Result<bool> {
 
-    if let Event::Key(key) = event::read()? {
+    if let Event::Mouse(mouse) = event {
+        handle_mouse(app, mouse);
+        return Ok(true);
+    }
+
+    if let Event::Key(key) = event {
 
         //block input
         if app.show_help {
@@ -23,6 +81,12 @@ pub fn handle_events(
             }
             return Ok(true);
         }
+        if app.show_log {
+            if let KeyCode::Esc = key.code {
+                app.show_log = false;
+            }
+            return Ok(true);
+        }
 
         //
         // INPUT MODE
@@ -31,7 +95,7 @@ pub fn handle_events(
             if let InputAction::ConfirmDelete = action {
                 match key.code {
                     KeyCode::Char('y') => {
-                        app.trash_selected()?;
+                        app.attempt("Move to trash", App::trash_selected);
                         app.mode = AppMode::Normal;
                         app.input.clear();
                     }
@@ -44,31 +108,54 @@ pub fn handle_events(
 
                 return Ok(true);
             }
+            if let InputAction::ResolveConflict = action {
+                match key.code {
+                    KeyCode::Char('o') => app.resolve_conflict(Some(ConflictPolicy::Overwrite)),
+                    KeyCode::Char('r') => app.resolve_conflict(Some(ConflictPolicy::Rename)),
+                    KeyCode::Char('s') => app.resolve_conflict(Some(ConflictPolicy::Skip)),
+                    KeyCode::Char('n') | KeyCode::Esc => app.resolve_conflict(None),
+                    _ => {}
+                }
+
+                return Ok(true);
+            }
             match key.code {
                 KeyCode::Enter => {
                     match action {
                         InputAction::Rename => {
-                            app.confirm_rename()?;
+                            app.attempt("Rename", App::confirm_rename);
                         }
 
                         InputAction::CreateFile => {
                             let name = app.input.clone();
                             if !name.is_empty() {
-                                app.create_file(&name)?;
+                                app.attempt("Create file", |app| app.create_file(&name));
                             }
                         }
 
                         InputAction::CreateFolder => {
                             let name = app.input.clone();
                             if !name.is_empty() {
-                                app.create_folder(&name)?;
+                                app.attempt("Create folder", |app| app.create_folder(&name));
                             }
                         }
 
                         InputAction::OpenWith => {
                             let program = app.input.clone();
                             if !program.is_empty() {
-                                app.open_with_program(&program)?;
+                                app.attempt("Open with", |app| app.open_with_program(&program));
+                            }
+                        }
+
+                        InputAction::Extract => {
+                            let folder = app.input.clone();
+                            app.extract_hovered(&folder);
+                        }
+
+                        InputAction::Compress => {
+                            let name = app.input.clone();
+                            if !name.is_empty() {
+                                app.compress_selection(&name);
                             }
                         }
 
@@ -111,6 +198,15 @@ pub fn handle_events(
                     };
                 }
             }
+            // cancel running jobs, otherwise drop marks and the message
+            KeyCode::Esc => {
+                if app.jobs.active.is_empty() {
+                    app.marked.clear();
+                    app.notices.dismiss();
+                } else {
+                    app.jobs.cancel_all();
+                }
+            }
             //show helper
             KeyCode::Char('/') => {
                 app.show_help = !app.show_help;
@@ -121,7 +217,7 @@ pub fn handle_events(
             //
             KeyCode::Down => match app.focus {
                 Focus::Files => {
-                    if app.selected + 1 < app.entries.len() {
+                    if app.selected + 1 < app.entry_count() {
                         app.selected += 1;
 
                         // reset preview state
@@ -143,7 +239,19 @@ pub fn handle_events(
             }
             //open with enter
             KeyCode::Enter => {
-                if config.keymaps.open == "enter" {
+                // picking: directories are browsed into, anything else is the choice
+                if app.chooser.is_some() && app.focus == Focus::Files {
+                    let is_dir = app.entries.get(app.selected).is_some_and(|e| e.is_dir);
+
+                    if is_dir && app.archive.is_none() {
+                        if let Some(name) = app.hovered_name() {
+                            app.cursor_memory.insert(app.current_dir.clone(), name);
+                        }
+                        app.attempt("Open", App::enter);
+                    } else if app.choose() {
+                        return Ok(false);
+                    }
+                } else if config.keymaps.open == "enter" && app.archive.is_none() {
                     app.start_input(InputAction::OpenWith, None);
                 }
             }
@@ -173,38 +281,54 @@ pub fn handle_events(
             KeyCode::Right => {
                 match app.focus {
                     Focus::Files => {
-                        app.cursor_memory
-                            .insert(app.current_dir.clone(), app.selected);
+                        if app.archive.is_none()
+                            && let Some(name) = app.hovered_name()
+                        {
+                            app.cursor_memory.insert(app.current_dir.clone(), name);
+                        }
 
-                        app.enter()?;
+                        app.attempt("Open", App::enter);
                     }
                     Focus::Pinned => {
-                        app.cursor_memory
-                            .insert(app.current_dir.clone(), app.selected);
+                        if let Some(name) = app.hovered_name() {
+                            app.cursor_memory.insert(app.current_dir.clone(), name);
+                        }
 
-                        app.open_pinned()?;
+                        app.attempt("Open pinned folder", App::open_pinned);
                     }
                 }
             }
-            KeyCode::Left => app.up()?,
+            KeyCode::Left => app.attempt("Go up", App::up),
 
             //
             // Keymap Controlled Actions
             //
             KeyCode::Char(c) => {
-                let pressed = c.to_string();
+                let pressed = if c == ' ' {
+                    "space".to_string()
+                } else {
+                    c.to_string()
+                };
+
+                // archive contents can be browsed and copied, nothing else
+                let read_only = app.archive.is_some();
 
                 // Quit
                 if pressed == config.keymaps.quit {
                     return Ok(false);
                 }
+                if pressed == config.keymaps.quit_cd {
+                    app.cd_on_exit = true;
+                    return Ok(false);
+                }
+                if pressed == config.keymaps.choose && app.choose() {
+                    return Ok(false);
+                }
 
                 // Rename
-                if pressed == config.keymaps.rename {
+                if pressed == config.keymaps.rename && !read_only {
                     if let Some(entry) = app.entries.get(app.selected) {
-                        if let Some(name) = entry.file_name().to_str() {
-                            app.start_input(InputAction::Rename, Some(name.to_string()));
-                        }
+                        app.start_input(InputAction::Rename, Some(entry.name.clone()));
                     }
                 }
                 if pressed == config.keymaps.focus {
@@ -214,28 +338,28 @@ pub fn handle_events(
                     };
                 }
                 // Create File
-                if pressed == config.keymaps.create_file {
+                if pressed == config.keymaps.create_file && !read_only {
                     app.start_input(InputAction::CreateFile, None);
                 }
 
                 // Create Folder
-                if pressed == config.keymaps.create_folder {
+                if pressed == config.keymaps.create_folder && !read_only {
                     app.start_input(InputAction::CreateFolder, None);
                 }
 
                 // Trash
-                if pressed == config.keymaps.trash {
+                if pressed == config.keymaps.trash && !read_only {
                     app.start_input(InputAction::ConfirmDelete, None);
                 }
 
                 // Open With
-                if pressed == config.keymaps.open {
+                if pressed == config.keymaps.open && !read_only {
                     app.start_input(InputAction::OpenWith, None);
                 }
 
                 // Sort
                 if pressed == config.keymaps.sort {
-                    app.cycle_sort()?;
+                    app.attempt("Sort", App::cycle_sort);
                 }
 
                 // Copy
@@ -247,21 +371,98 @@ pub fn handle_events(
                     app.cut_selected();
                 }
                 //Paste
-                if pressed == config.keymaps.paste {
-                    app.paste()?;
+                if pressed == config.keymaps.paste && !read_only {
+                    app.attempt("Paste", App::paste);
                 }
                 // Toggle Hidden
                 if pressed == config.keymaps.toggle_hidden {
-                    app.toggle_hidden()?;
+                    app.attempt("Toggle hidden", App::toggle_hidden);
                 }
 
-                if pressed == config.keymaps.pin && app.focus == Focus::Files {
+                if pressed == config.keymaps.pin && app.focus == Focus::Files && !read_only {
                     app.pin_selected();
                 }
 
                 if pressed == config.keymaps.unpin && app.focus == Focus::Pinned {
                     app.unpin_selected();
                 }
+
+                // Mark
+                if pressed == config.keymaps.mark && !read_only {
+                    app.toggle_mark();
+                }
+
+                // Extract archive
+                if pressed == config.keymaps.extract
+                    && !read_only
+                    && let Some(archive) = app.hovered_archive()
+                {
+                    app.start_input(InputAction::Extract, Some(crate::archive::stem(&archive)));
+                }
+
+                // Compress selection
+                if pressed == config.keymaps.compress && !read_only {
+                    let selection = app.selection();
+                    let base = match selection.as_slice() {
+                        [single] => single.file_name().map(|n| n.to_string_lossy().into_owned()),
+                        _ => app.current_dir.file_name().map(|n| n.to_string_lossy().into_owned()),
+                    };
+                    if !selection.is_empty() {
+                        let name = format!("{}.zip", base.unwrap_or_else(|| "archive".to_string()));
+                        app.start_input(InputAction::Compress, Some(name));
+                    }
+                }
+
+                // Scroll preview
+                if pressed == config.keymaps.preview_down {
+                    app.scroll_preview(1);
+                }
+                if pressed == config.keymaps.preview_up {
+                    app.scroll_preview(-1);
+                }
+                if pressed == config.keymaps.preview_half_down {
+                    app.scroll_preview(app.preview_half_page());
+                }
+                if pressed == config.keymaps.preview_half_up {
+                    app.scroll_preview(-app.preview_half_page());
+                }
+                if pressed == config.keymaps.preview_top {
+                    app.preview_scroll = 0;
+                    app.text_window = 0;
+                }
+                // clamped to the real end while drawing
+                if pressed == config.keymaps.preview_bottom {
+                    app.preview_scroll = usize::MAX;
+                }
+
+                if pressed == config.keymaps.messages {
+                    app.show_log = true;
+                }
+
+                if pressed == config.keymaps.toggle_exif {
+                    app.exif_expanded = !app.exif_expanded;
+                }
+
+                // how deep the data tree unfolds
+                if pressed == config.keymaps.tree_expand {
+                    app.tree_depth += 1;
+                }
+                if pressed == config.keymaps.tree_collapse {
+                    app.tree_depth = app.tree_depth.saturating_sub(1);
+                }
+
+                // rendered vs source view
+                if pressed == config.keymaps.preview_raw {
+                    app.preview_raw = !app.preview_raw;
+                }
+
+                // PDF pages
+                if pressed == config.keymaps.next_page {
+                    app.change_page(1);
+                }
+                if pressed == config.keymaps.prev_page {
+                    app.change_page(-1);
+                }
             }
 
             _ => {}
@@ -270,3 +471,16 @@ pub fn handle_events(
 
     Ok(true)
 }
+
+// wheel over the preview scrolls it
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    if !app.preview_rect.contains(Position::new(mouse.column, mouse.row)) {
+        return;
+    }
+
+    match mouse.kind {
+        MouseEventKind::ScrollDown => app.scroll_preview(3),
+        MouseEventKind::ScrollUp => app.scroll_preview(-3),
+        _ => {}
+    }
+}
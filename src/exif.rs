@@ -0,0 +1,475 @@
+use std::{fs::File, io::Read, path::Path};
+
+// metadata lives near the start of the file in every format we read
+const READ_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Clone, Default)]
+pub struct Exif {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    pub exposure: Option<(u32, u32)>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    pub focal_length: Option<f64>,
+    pub taken: Option<String>,
+    pub gps: Option<(f64, f64)>,
+    pub altitude: Option<f64>,
+    pub orientation: Option<u16>,
+}
+
+impl Exif {
+    pub fn is_empty(&self) -> bool {
+        self.camera().is_none()
+            && self.lens.is_none()
+            && self.exposure.is_none()
+            && self.taken.is_none()
+            && self.gps.is_none()
+            && self.orientation.is_none()
+    }
+
+    /// "Make Model", without repeating the make when the model already has it.
+    pub fn camera(&self) -> Option<String> {
+        match (&self.make, &self.model) {
+            (Some(make), Some(model)) if model.starts_with(make.as_str()) => Some(model.clone()),
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.clone().or_else(|| model.clone()),
+        }
+    }
+
+    /// "1/250s  f/2.8  ISO 100  50mm", skipping whatever is missing.
+    pub fn settings(&self) -> Option<String> {
+        let mut parts = Vec::new();
+
+        if let Some((n, d)) = self.exposure {
+            parts.push(if n >= d || d == 0 {
+                format!("{}s", n as f64 / d.max(1) as f64)
+            } else {
+                format!("1/{}s", (d as f64 / n.max(1) as f64).round())
+            });
+        }
+        if let Some(f) = self.f_number {
+            parts.push(format!("f/{:.1}", f));
+        }
+        if let Some(iso) = self.iso {
+            parts.push(format!("ISO {}", iso));
+        }
+        if let Some(focal) = self.focal_length {
+            parts.push(format!("{}mm", focal.round()));
+        }
+
+        (!parts.is_empty()).then(|| parts.join("  "))
+    }
+
+    pub fn orientation_name(&self) -> Option<&'static str> {
+        Some(match self.orientation? {
+            1 => "Normal",
+            2 => "Mirrored",
+            3 => "Rotated 180°",
+            4 => "Flipped",
+            5 => "Mirrored, rotated 90° CCW",
+            6 => "Rotated 90° CW",
+            7 => "Mirrored, rotated 90° CW",
+            8 => "Rotated 90° CCW",
+            _ => return None,
+        })
+    }
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    matches!(
+        ext.as_str(),
+        "jpg" | "jpeg" | "tif" | "tiff" | "png" | "webp" | "heic" | "heif" | "avif"
+    )
+}
+
+/// EXIF and XMP from JPEG, TIFF, PNG, WebP and HEIC files.
+pub fn read(path: &Path) -> Option<Exif> {
+    let mut data = Vec::new();
+    File::open(path).ok()?.take(READ_LIMIT).read_to_end(&mut data).ok()?;
+
+    let (tiff, xmp) = match data.get(..4)? {
+        [0xff, 0xd8, ..] => jpeg_segments(&data),
+        b"II*\0" | b"MM\0*" => (Some(&data[..]), find_xmp(&data)),
+        [0x89, b'P', b'N', b'G'] => png_chunks(&data),
+        b"RIFF" => webp_chunks(&data),
+        // HEIC and friends: the Exif item is easier to find than to locate properly
+        _ => (scan_exif(&data), find_xmp(&data)),
+    };
+
+    let mut exif = tiff.and_then(parse_tiff).unwrap_or_default();
+
+    if let Some(xmp) = xmp {
+        merge_xmp(&mut exif, xmp);
+    }
+
+    (!exif.is_empty()).then_some(exif)
+}
+
+//
+// Containers
+//
+type Found<'a> = (Option<&'a [u8]>, Option<&'a str>);
+
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+fn jpeg_segments(data: &[u8]) -> Found<'_> {
+    let (mut tiff, mut xmp) = (None, None);
+    let mut pos = 2;
+
+    while pos + 4 <= data.len() && data[pos] == 0xff {
+        let marker = data[pos + 1];
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+
+        // start of scan: image data follows, no more metadata
+        if marker == 0xda {
+            break;
+        }
+
+        let Some(body) = data.get(pos + 4..pos + 2 + len) else {
+            break;
+        };
+
+        if marker == 0xe1 {
+            if let Some(rest) = body.strip_prefix(b"Exif\0\0") {
+                tiff = tiff.or(Some(rest));
+            } else if let Some(rest) = body.strip_prefix(XMP_HEADER) {
+                xmp = xmp.or(std::str::from_utf8(rest).ok());
+            }
+        }
+
+        pos += 2 + len;
+    }
+
+    (tiff, xmp)
+}
+
+fn png_chunks(data: &[u8]) -> Found<'_> {
+    let (mut tiff, mut xmp) = (None, None);
+    let mut pos = 8;
+
+    while let Some(header) = data.get(pos..pos + 8) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let Some(body) = data.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+
+        match &header[4..8] {
+            b"eXIf" => tiff = Some(body),
+            b"iTXt" if body.starts_with(b"XML:com.adobe.xmp\0") => xmp = find_xmp(body),
+            b"IDAT" => break,
+            _ => {}
+        }
+
+        pos += 12 + len;
+    }
+
+    (tiff, xmp)
+}
+
+fn webp_chunks(data: &[u8]) -> Found<'_> {
+    let (mut tiff, mut xmp) = (None, None);
+    let mut pos = 12;
+
+    while let Some(header) = data.get(pos..pos + 8) {
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let Some(body) = data.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+
+        match &header[0..4] {
+            // some writers keep the JPEG-style prefix
+            b"EXIF" => tiff = Some(body.strip_prefix(b"Exif\0\0").unwrap_or(body)),
+            b"XMP " => xmp = std::str::from_utf8(body).ok(),
+            _ => {}
+        }
+
+        pos += 8 + len + (len & 1);
+    }
+
+    (tiff, xmp)
+}
+
+fn scan_exif(data: &[u8]) -> Option<&[u8]> {
+    let at = data.windows(10).position(|w| {
+        w.starts_with(b"Exif\0\0") && (&w[6..] == b"II*\0" || &w[6..] == b"MM\0*")
+    })?;
+
+    Some(&data[at + 6..])
+}
+
+fn find_xmp(data: &[u8]) -> Option<&str> {
+    let start = find(data, b"<x:xmpmeta")?;
+    let end = find(&data[start..], b"</x:xmpmeta>")? + start;
+
+    std::str::from_utf8(&data[start..end]).ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+//
+// TIFF / IFD parsing
+//
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, at: usize) -> Option<u16> {
+        let b = self.data.get(at..at + 2)?;
+        Some(if self.big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b = self.data.get(at..at + 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Some(if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    }
+
+    /// (tag, type, count, offset of the value) for each entry in the IFD.
+    fn entries(&self, ifd: usize) -> Vec<(u16, u16, u32, usize)> {
+        let Some(count) = self.u16(ifd) else {
+            return Vec::new();
+        };
+
+        (0..count as usize)
+            .filter_map(|i| {
+                let at = ifd + 2 + i * 12;
+                let tag = self.u16(at)?;
+                let kind = self.u16(at + 2)?;
+                let n = self.u32(at + 4)?;
+
+                // values of four bytes or less are stored inline
+                let size = type_size(kind)? * n as usize;
+                let value = if size <= 4 { at + 8 } else { self.u32(at + 8)? as usize };
+
+                Some((tag, kind, n, value))
+            })
+            .collect()
+    }
+
+    fn ascii(&self, at: usize, n: u32) -> Option<String> {
+        let bytes = self.data.get(at..at + n as usize)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn rational(&self, at: usize) -> Option<(u32, u32)> {
+        Some((self.u32(at)?, self.u32(at + 4)?))
+    }
+
+    fn float(&self, at: usize) -> Option<f64> {
+        let (n, d) = self.rational(at)?;
+        (d != 0).then(|| n as f64 / d as f64)
+    }
+
+    fn number(&self, kind: u16, at: usize) -> Option<u32> {
+        match kind {
+            1 | 7 => self.data.get(at).map(|&b| b as u32),
+            3 => self.u16(at).map(u32::from),
+            4 | 9 => self.u32(at),
+            _ => None,
+        }
+    }
+}
+
+fn type_size(kind: u16) -> Option<usize> {
+    match kind {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+fn parse_tiff(data: &[u8]) -> Option<Exif> {
+    let big_endian = match data.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+
+    let tiff = Tiff { data, big_endian };
+    let mut exif = Exif::default();
+    let (mut exif_ifd, mut gps_ifd) = (None, None);
+
+    for (tag, kind, n, at) in tiff.entries(tiff.u32(4)? as usize) {
+        match tag {
+            0x010f => exif.make = tiff.ascii(at, n),
+            0x0110 => exif.model = tiff.ascii(at, n),
+            0x0112 => exif.orientation = tiff.u16(at),
+            0x0132 => exif.taken = exif.taken.or(tiff.ascii(at, n)),
+            0x8769 => exif_ifd = tiff.number(kind, at),
+            0x8825 => gps_ifd = tiff.number(kind, at),
+            _ => {}
+        }
+    }
+
+    for (tag, kind, n, at) in exif_ifd.map(|o| tiff.entries(o as usize)).unwrap_or_default() {
+        match tag {
+            0x829a => exif.exposure = tiff.rational(at),
+            0x829d => exif.f_number = tiff.float(at),
+            0x8827 => exif.iso = tiff.number(kind, at),
+            // the original capture time beats the last-modified DateTime
+            0x9003 => exif.taken = tiff.ascii(at, n).or(exif.taken.take()),
+            0x920a => exif.focal_length = tiff.float(at),
+            0xa434 => exif.lens = tiff.ascii(at, n),
+            _ => {}
+        }
+    }
+
+    let mut lat = None;
+    let mut lon = None;
+    let mut lat_ref = b'N';
+    let mut lon_ref = b'E';
+
+    for (tag, _, _, at) in gps_ifd.map(|o| tiff.entries(o as usize)).unwrap_or_default() {
+        match tag {
+            1 => lat_ref = data.get(at).copied().unwrap_or(b'N'),
+            2 => lat = degrees(&tiff, at),
+            3 => lon_ref = data.get(at).copied().unwrap_or(b'E'),
+            4 => lon = degrees(&tiff, at),
+            6 => exif.altitude = tiff.float(at),
+            _ => {}
+        }
+    }
+
+    if let (Some(lat), Some(lon)) = (lat, lon) {
+        let lat = if lat_ref == b'S' { -lat } else { lat };
+        let lon = if lon_ref == b'W' { -lon } else { lon };
+        exif.gps = Some((lat, lon));
+    }
+
+    Some(exif)
+}
+
+// degrees, minutes, seconds as three rationals
+fn degrees(tiff: &Tiff, at: usize) -> Option<f64> {
+    let d = tiff.float(at)?;
+    let m = tiff.float(at + 8).unwrap_or(0.0);
+    let s = tiff.float(at + 16).unwrap_or(0.0);
+
+    Some(d + m / 60.0 + s / 3600.0)
+}
+
+//
+// XMP: only fills in what EXIF didn't have
+//
+fn merge_xmp(exif: &mut Exif, xmp: &str) {
+    let get = |name: &str| xmp_value(xmp, name);
+
+    exif.make = exif.make.take().or_else(|| get("tiff:Make"));
+    exif.model = exif.model.take().or_else(|| get("tiff:Model"));
+    exif.lens = exif.lens.take().or_else(|| get("exifEX:LensModel")).or_else(|| get("aux:Lens"));
+    exif.taken = exif.taken.take().or_else(|| get("exif:DateTimeOriginal")).or_else(|| get("xmp:CreateDate"));
+    exif.orientation = exif.orientation.or_else(|| get("tiff:Orientation")?.parse().ok());
+    exif.iso = exif.iso.or_else(|| get("exif:ISOSpeedRatings")?.parse().ok());
+    exif.f_number = exif.f_number.or_else(|| xmp_rational(&get("exif:FNumber")?));
+    exif.focal_length = exif.focal_length.or_else(|| xmp_rational(&get("exif:FocalLength")?));
+
+    if exif.exposure.is_none()
+        && let Some(value) = get("exif:ExposureTime")
+        && let Some((n, d)) = value.split_once('/')
+    {
+        exif.exposure = n.parse().ok().zip(d.parse().ok());
+    }
+
+    if exif.gps.is_none() {
+        let lat = get("exif:GPSLatitude").and_then(|v| xmp_coordinate(&v));
+        let lon = get("exif:GPSLongitude").and_then(|v| xmp_coordinate(&v));
+        exif.gps = lat.zip(lon);
+    }
+}
+
+// attribute form `name="value"` or element form `<name>value</name>`,
+// where the value may be wrapped in an rdf:Seq
+fn xmp_value(xmp: &str, name: &str) -> Option<String> {
+    let attr = format!("{}=\"", name);
+    if let Some(start) = xmp.find(&attr) {
+        let rest = &xmp[start + attr.len()..];
+        return Some(rest[..rest.find('"')?].trim().to_string());
+    }
+
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = xmp.find(&open)? + open.len();
+    let inner = &xmp[start..start + xmp[start..].find(&close)?];
+
+    let inner = match inner.find("<rdf:li") {
+        Some(li) => {
+            let rest = &inner[li..];
+            let begin = rest.find('>')? + 1;
+            // the close tag has to come after the open one
+            &rest[begin..begin + rest[begin..].find("</rdf:li>")?]
+        }
+        None => inner,
+    };
+
+    let value = inner.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn xmp_rational(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((n, d)) => {
+            let (n, d): (f64, f64) = (n.parse().ok()?, d.parse().ok()?);
+            (d != 0.0).then(|| n / d)
+        }
+        None => value.parse().ok(),
+    }
+}
+
+// "51,30.5N": degrees, decimal minutes and a hemisphere letter
+fn xmp_coordinate(value: &str) -> Option<f64> {
+    let hemisphere = value.chars().last()?;
+    let (deg, min) = value.strip_suffix(['N', 'S', 'E', 'W'])?.split_once(',')?;
+    let coordinate = deg.parse::<f64>().ok()? + min.parse::<f64>().ok()? / 60.0;
+
+    Some(if matches!(hemisphere, 'S' | 'W') { -coordinate } else { coordinate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_xmp_coordinates() {
+        assert_eq!(xmp_coordinate("52,30.0N"), Some(52.5));
+        assert_eq!(xmp_coordinate("13,15.0W"), Some(-13.25));
+    }
+
+    #[test]
+    fn odd_xmp_coordinates_are_refused() {
+        for value in ["", "N", "52,30", "52,30.0°", "52°30.0", "52,30.0ä"] {
+            assert_eq!(xmp_coordinate(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn reads_xmp_values() {
+        let xmp = r#"<x exif:GPSLatitude="52,30.0N"><dc:title><rdf:Alt><rdf:li xml:lang="x-default">Harbour</rdf:li></rdf:Alt></dc:title></x>"#;
+
+        assert_eq!(xmp_value(xmp, "exif:GPSLatitude").as_deref(), Some("52,30.0N"));
+        assert_eq!(xmp_value(xmp, "dc:title").as_deref(), Some("Harbour"));
+        assert_eq!(xmp_value(xmp, "dc:creator"), None);
+    }
+
+    #[test]
+    fn misordered_xmp_tags_are_refused() {
+        // the first `>` is the close tag's own, so it comes before the value
+        let xmp = r#"<dc:title><rdf:li a="</rdf:li>">Harbour</dc:title>"#;
+        assert_eq!(xmp_value(xmp, "dc:title"), None);
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+
+use crate::theme::Theme;
+
+//
+// Layout
+//
+const OFFSET_WIDTH: usize = 8;
+const GROUP: usize = 8;
+
+/// How many bytes fit on one row of a preview `width` columns wide.
+pub fn bytes_per_row(width: u16) -> usize {
+    [16, 8, 4]
+        .into_iter()
+        .find(|&n| row_width(n) <= width as usize)
+        .unwrap_or(4)
+}
+
+// offset + gap + "xx " per byte + group gaps + "|ascii|"
+fn row_width(n: usize) -> usize {
+    OFFSET_WIDTH + 2 + n * 3 + (n / GROUP).saturating_sub(1) + 1 + n + 1
+}
+
+//
+// Sniffing
+//
+/// Treat a file as binary when its first block contains a NUL byte.
+pub fn looks_binary(path: &Path) -> bool {
+    let mut buf = [0u8; 1024];
+
+    match File::open(path).and_then(|mut f| f.read(&mut buf)) {
+        Ok(n) => buf[..n].contains(&0),
+        Err(_) => false,
+    }
+}
+
+//
+// Reading
+//
+/// Read at most `len` bytes starting at `offset`, without touching the rest of the file.
+pub fn read_window(path: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut buf = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut buf)?;
+
+    Ok(buf)
+}
+
+//
+// Rendering
+//
+pub fn render(bytes: &[u8], base: u64, per_row: usize, theme: &Theme) -> Vec<Line<'static>> {
+    bytes
+        .chunks(per_row)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let offset = base + (row * per_row) as u64;
+            let mut spans = vec![Span::styled(
+                format!("{:0width$x}  ", offset, width = OFFSET_WIDTH),
+                Style::default().fg(theme.muted),
+            )];
+
+            for i in 0..per_row {
+                if i > 0 && i % GROUP == 0 {
+                    spans.push(Span::raw(" "));
+                }
+
+                match chunk.get(i) {
+                    Some(&b) => spans.push(Span::styled(
+                        format!("{:02x} ", b),
+                        Style::default().fg(byte_color(b, theme)),
+                    )),
+                    None => spans.push(Span::raw("   ")),
+                }
+            }
+
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+
+            spans.push(Span::styled("|", Style::default().fg(theme.muted)));
+            spans.push(Span::styled(ascii, Style::default().fg(theme.foreground)));
+            spans.push(Span::styled("|", Style::default().fg(theme.muted)));
+
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn byte_color(b: u8, theme: &Theme) -> ratatui::style::Color {
+    if b == 0 {
+        theme.muted
+    } else if b.is_ascii_graphic() || b == b' ' {
+        theme.foreground
+    } else {
+        theme.directory
+    }
+}
@@ -0,0 +1,152 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+
+use crate::theme::Theme;
+
+//
+// Per-language rules
+//
+struct Syntax {
+    keywords: &'static [&'static str],
+    line_comment: &'static str,
+    // single quotes only wrap one char, so lifetimes aren't strings
+    char_literals: bool,
+}
+
+const RUST: Syntax = Syntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: "//",
+    char_literals: true,
+};
+
+const C_LIKE: Syntax = Syntax {
+    keywords: &[
+        "break", "case", "class", "const", "continue", "default", "do", "else", "enum",
+        "export", "extends", "false", "for", "func", "function", "go", "if", "import", "int",
+        "interface", "let", "new", "null", "package", "return", "static", "struct", "switch",
+        "this", "true", "type", "var", "void", "while",
+    ],
+    line_comment: "//",
+    char_literals: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    keywords: &[
+        "and", "as", "class", "def", "elif", "else", "except", "False", "finally", "for",
+        "from", "if", "import", "in", "is", "lambda", "None", "not", "or", "pass", "raise",
+        "return", "True", "try", "while", "with", "yield",
+    ],
+    line_comment: "#",
+    char_literals: false,
+};
+
+const SHELL: Syntax = Syntax {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "while",
+    ],
+    line_comment: "#",
+    char_literals: false,
+};
+
+const PLAIN: Syntax = Syntax {
+    keywords: &[],
+    line_comment: "",
+    char_literals: false,
+};
+
+fn syntax(lang: &str) -> &'static Syntax {
+    match lang.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => &RUST,
+        "c" | "cpp" | "c++" | "h" | "go" | "java" | "js" | "javascript" | "ts" | "typescript" => &C_LIKE,
+        "python" | "py" => &PYTHON,
+        "sh" | "bash" | "zsh" | "shell" | "console" | "toml" | "yaml" | "yml" => &SHELL,
+        _ => &PLAIN,
+    }
+}
+
+/// Colour one line of code: keywords, strings, numbers and line comments.
+pub fn line(code: &str, lang: &str, theme: &Theme) -> Vec<Span<'static>> {
+    let syntax = syntax(lang);
+
+    let plain = Style::default().fg(theme.foreground);
+    let keyword = Style::default().fg(theme.focus_border).add_modifier(Modifier::BOLD);
+    let string = Style::default().fg(theme.directory);
+    let number = Style::default().fg(theme.status_fg);
+    let comment = Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC);
+
+    let mut spans = Vec::new();
+    let mut rest = code;
+
+    let starts_comment = |s: &str| !syntax.line_comment.is_empty() && s.starts_with(syntax.line_comment);
+    let starts_string = |s: &str| match s.chars().next() {
+        Some('"' | '`') => true,
+        Some('\'') if syntax.char_literals => s[1..].chars().take(3).skip(1).any(|ch| ch == '\''),
+        Some('\'') => true,
+        _ => false,
+    };
+
+    while let Some(c) = rest.chars().next() {
+        if starts_comment(rest) {
+            spans.push(Span::styled(rest.to_string(), comment));
+            break;
+        }
+
+        let len = if starts_string(rest) {
+            // up to the matching quote, skipping escapes
+            let mut escaped = false;
+            let end = rest[1..]
+                .char_indices()
+                .find(|&(_, ch)| {
+                    let done = ch == c && !escaped;
+                    escaped = ch == '\\' && !escaped;
+                    done
+                })
+                .map(|(i, _)| i + 2)
+                .unwrap_or(rest.len());
+
+            spans.push(Span::styled(rest[..end].to_string(), string));
+            end
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .unwrap_or(rest.len());
+
+            spans.push(Span::styled(rest[..end].to_string(), number));
+            end
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let style = if syntax.keywords.contains(&word) { keyword } else { plain };
+
+            spans.push(Span::styled(word.to_string(), style));
+            end
+        } else {
+            // punctuation and spaces up to the next interesting token
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|&(i, ch)| {
+                    ch.is_alphanumeric() || ch == '_' || starts_string(&rest[i..]) || starts_comment(&rest[i..])
+                })
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+
+            spans.push(Span::styled(rest[..end].to_string(), plain));
+            end
+        };
+
+        rest = &rest[len..];
+    }
+
+    spans
+}
@@ -0,0 +1,165 @@
+use std::{
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::archive::{ArchiveTask, ConflictPolicy, Outcome};
+use crate::event::AppEvent;
+
+// progress updates are coalesced to this rate
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+pub struct Job {
+    pub id: u64,
+    pub label: String,
+    pub done: u64,
+    pub total: u64,
+    pub current: String,
+    task: ArchiveTask,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn percent(&self) -> u64 {
+        (self.done * 100).checked_div(self.total).unwrap_or(0)
+    }
+}
+
+enum JobEvent {
+    Progress {
+        id: u64,
+        done: u64,
+        total: u64,
+        current: String,
+    },
+    Finished {
+        id: u64,
+        result: io::Result<Outcome>,
+    },
+}
+
+pub enum Finished {
+    Done(String),
+    Conflict(ArchiveTask, Vec<String>),
+    Failed(String),
+}
+
+pub struct Jobs {
+    pub active: Vec<Job>,
+    next_id: u64,
+    tx: Sender<JobEvent>,
+    rx: Receiver<JobEvent>,
+    waker: Sender<AppEvent>,
+}
+
+impl Jobs {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            active: Vec::new(),
+            next_id: 0,
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    pub fn spawn(&mut self, task: ArchiveTask, policy: Option<ConflictPolicy>) {
+        self.next_id += 1;
+
+        let id = self.next_id;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        let worker_task = task.clone();
+        let tx = self.tx.clone();
+        let waker = self.waker.clone();
+
+        self.active.push(Job {
+            id,
+            label: task.label(),
+            done: 0,
+            total: 0,
+            current: String::new(),
+            task,
+            cancel,
+        });
+
+        thread::spawn(move || {
+            let mut last_sent = Instant::now() - PROGRESS_INTERVAL;
+
+            let mut progress = |done, total, current: &str| {
+                if worker_cancel.load(Ordering::Relaxed) {
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+                }
+
+                if last_sent.elapsed() >= PROGRESS_INTERVAL {
+                    last_sent = Instant::now();
+                    let _ = tx.send(JobEvent::Progress {
+                        id,
+                        done,
+                        total,
+                        current: current.to_string(),
+                    });
+                    let _ = waker.send(AppEvent::Worker);
+                }
+
+                Ok(())
+            };
+
+            // the panic hook reports the details, the job still has to end
+            let result = panic::catch_unwind(AssertUnwindSafe(|| worker_task.run(policy, &mut progress)))
+                .unwrap_or_else(|_| Err(io::Error::other("it crashed")));
+
+            let _ = tx.send(JobEvent::Finished { id, result });
+            let _ = waker.send(AppEvent::Worker);
+        });
+    }
+
+    /// Apply pending progress and collect jobs that ended since the last poll.
+    pub fn poll(&mut self) -> Vec<Finished> {
+        let mut finished = Vec::new();
+
+        while let Ok(event) = self.rx.try_recv() {
+            match event {
+                JobEvent::Progress { id, done, total, current } => {
+                    if let Some(job) = self.active.iter_mut().find(|j| j.id == id) {
+                        job.done = done;
+                        job.total = total;
+                        job.current = current;
+                    }
+                }
+                JobEvent::Finished { id, result } => {
+                    let Some(pos) = self.active.iter().position(|j| j.id == id) else {
+                        continue;
+                    };
+                    let job = self.active.remove(pos);
+
+                    finished.push(match result {
+                        Ok(Outcome::Done(message)) => Finished::Done(message),
+                        Ok(Outcome::Conflict(names)) => Finished::Conflict(job.task, names),
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                            Finished::Failed(format!("{} cancelled", job.label))
+                        }
+                        Err(e) => Finished::Failed(format!("{} failed: {}", job.label, e)),
+                    });
+                }
+            }
+        }
+
+        finished
+    }
+
+    pub fn cancel_all(&self) {
+        for job in &self.active {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
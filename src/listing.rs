@@ -0,0 +1,329 @@
+use std::{
+    cmp::Ordering,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{self, AtomicU64},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::app::SortMode;
+use crate::cache::FileStamp;
+use crate::entry::{self, Entry};
+use crate::event::AppEvent;
+
+// big directories arrive in batches of this many entries
+const CHUNK: usize = 1000;
+// quick loads finish before the indicator would flash up
+const INDICATOR_DELAY: Duration = Duration::from_millis(150);
+
+pub enum Loaded {
+    /// More entries of the directory being loaded, in no particular order
+    Chunk(Vec<Entry>),
+    /// The whole listing, sorted
+    Done(io::Result<Vec<Entry>>),
+}
+
+//
+// Background listing
+//
+/// Lists directories on a worker thread so huge folders and slow mounts
+/// don't block the UI. Starting a new load abandons the one in flight.
+pub struct DirLoader {
+    // when the current load started and how many entries have arrived so far
+    loading: Option<(Instant, usize)>,
+    generation: Arc<AtomicU64>,
+    tx: Sender<(u64, Loaded)>,
+    rx: Receiver<(u64, Loaded)>,
+    waker: Sender<AppEvent>,
+}
+
+impl DirLoader {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            loading: None,
+            generation: Arc::new(AtomicU64::new(0)),
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    pub fn load(&mut self, dir: &Path, mode: SortMode, show_hidden: bool) {
+        let generation = self.generation.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+        self.loading = Some((Instant::now(), 0));
+
+        let current = self.generation.clone();
+        let dir = dir.to_path_buf();
+        let tx = self.tx.clone();
+        let waker = self.waker.clone();
+
+        thread::spawn(move || {
+            let cancelled = || current.load(atomic::Ordering::Relaxed) != generation;
+
+            let result = list(&dir, show_hidden, &cancelled, &mut |chunk| {
+                let _ = tx.send((generation, Loaded::Chunk(chunk)));
+                let _ = waker.send(AppEvent::Worker);
+            });
+
+            // nobody is waiting for this one any more
+            if cancelled() {
+                return;
+            }
+
+            let result = result.map(|mut entries| {
+                sort(&mut entries, mode);
+                entries
+            });
+
+            let _ = tx.send((generation, Loaded::Done(result)));
+            let _ = waker.send(AppEvent::Worker);
+        });
+    }
+
+    /// Entries loaded so far, once a load has been running long enough to show it.
+    pub fn progress(&self) -> Option<usize> {
+        self.loading
+            .filter(|(started, _)| started.elapsed() >= INDICATOR_DELAY)
+            .map(|(_, count)| count)
+    }
+
+    /// When `progress` starts reporting the current load.
+    pub fn indicator_at(&self) -> Option<Instant> {
+        self.loading.map(|(started, _)| started + INDICATOR_DELAY)
+    }
+
+    /// What the current load produced since the last call.
+    pub fn poll(&mut self) -> Vec<Loaded> {
+        let current = self.generation.load(atomic::Ordering::Relaxed);
+        let mut loaded = Vec::new();
+
+        while let Ok((generation, event)) = self.rx.try_recv() {
+            if generation != current {
+                continue;
+            }
+
+            match &event {
+                Loaded::Chunk(chunk) => {
+                    if let Some((_, count)) = &mut self.loading {
+                        *count += chunk.len();
+                    }
+                }
+                Loaded::Done(_) => self.loading = None,
+            }
+            loaded.push(event);
+        }
+
+        loaded
+    }
+}
+
+//
+// Hovered directory
+//
+// directory, its stamp and whether dotfiles are shown
+type PreviewKey = (PathBuf, FileStamp, bool);
+
+/// The listing of the directory under the cursor for the preview panel,
+/// loaded in the background and kept until the directory changes.
+pub struct DirPreview {
+    loader: DirLoader,
+    wanted: Option<PreviewKey>,
+    listing: Option<(PreviewKey, Result<Vec<Entry>, String>)>,
+}
+
+impl DirPreview {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        Self {
+            loader: DirLoader::new(waker),
+            wanted: None,
+            listing: None,
+        }
+    }
+
+    /// The sorted listing of `dir`, or `None` while it loads.
+    pub fn request(&mut self, dir: &Path, stamp: FileStamp, show_hidden: bool) -> Option<&Result<Vec<Entry>, String>> {
+        for loaded in self.loader.poll() {
+            if let Loaded::Done(result) = loaded
+                && let Some(key) = self.wanted.take()
+            {
+                self.listing = Some((key, result.map_err(|e| e.to_string())));
+            }
+        }
+
+        let key = (dir.to_path_buf(), stamp, show_hidden);
+
+        if self.listing.as_ref().is_some_and(|(k, _)| *k == key) {
+            return self.listing.as_ref().map(|(_, listing)| listing);
+        }
+
+        if self.wanted.as_ref() != Some(&key) {
+            self.loader.load(dir, SortMode::Name, show_hidden);
+            self.wanted = Some(key);
+        }
+
+        None
+    }
+
+    /// Entries loaded so far, once the load has been running long enough to show it.
+    pub fn progress(&self) -> Option<usize> {
+        self.loader.progress()
+    }
+
+    pub fn indicator_at(&self) -> Option<Instant> {
+        self.wanted.as_ref().and(self.loader.indicator_at())
+    }
+
+    pub fn invalidate(&mut self, dir: &Path) {
+        if self.listing.as_ref().is_some_and(|((d, _, _), _)| d == dir) {
+            self.listing = None;
+        }
+        if self.wanted.as_ref().is_some_and(|(d, _, _)| d == dir) {
+            self.wanted = None;
+        }
+    }
+}
+
+fn list(
+    dir: &Path,
+    show_hidden: bool,
+    cancelled: &dyn Fn() -> bool,
+    send: &mut dyn FnMut(Vec<Entry>),
+) -> io::Result<Vec<Entry>> {
+    let owners = entry::owners();
+
+    let mut entries = Vec::new();
+    let mut sent = 0;
+
+    for e in fs::read_dir(dir)?.filter_map(Result::ok) {
+        if cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+
+        if !show_hidden && e.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        entries.push(Entry::new(&e, &owners));
+
+        if entries.len() - sent == CHUNK {
+            send(entries[sent..].to_vec());
+            sent = entries.len();
+        }
+    }
+
+    if sent < entries.len() {
+        send(entries[sent..].to_vec());
+    }
+
+    Ok(entries)
+}
+
+fn sort(entries: &mut [Entry], mode: SortMode) {
+    //
+    // PRIMARY SORT
+    //
+    match mode {
+        SortMode::Name => {
+            entries.sort_by(|a, b| natord::compare_ignore_case(&a.name, &b.name));
+        }
+        SortMode::Size => {
+            entries.sort_by_key(|e| e.size);
+        }
+        SortMode::Modified => {
+            entries.sort_by_key(|e| e.modified);
+        }
+    }
+
+    //
+    // SECONDARY SORT: directories first (stable)
+    //
+    entries.sort_by(|a, b| {
+        if a.is_dir != b.is_dir {
+            return if a.is_dir {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            };
+        }
+
+        Ordering::Equal // keep previous ordering within groups
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fren-listing-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for file in ["b.txt", "a10.txt", "a2.txt", ".hidden"] {
+            fs::write(dir.join(file), file).unwrap();
+        }
+        dir
+    }
+
+    // chunks wake the UI too, so wait until the whole listing is in
+    fn listed(preview: &mut DirPreview, woken: &Receiver<AppEvent>, dir: &Path, show_hidden: bool) -> Result<Vec<String>, String> {
+        let stamp = FileStamp::of(dir);
+        loop {
+            if let Some(listing) = preview.request(dir, stamp, show_hidden) {
+                return listing.as_ref().map(|entries| entries.iter().map(|e| e.name.clone()).collect()).map_err(Clone::clone);
+            }
+            woken.recv().unwrap();
+        }
+    }
+
+    #[test]
+    fn hovered_directory_is_listed_in_the_background() {
+        let dir = scratch("preview");
+        let (waker, woken) = mpsc::channel();
+        let mut preview = DirPreview::new(waker);
+        let stamp = FileStamp::of(&dir);
+
+        assert!(preview.request(&dir, stamp, false).is_none());
+        assert_eq!(listed(&mut preview, &woken, &dir, false).unwrap(), ["sub", "a2.txt", "a10.txt", "b.txt"]);
+
+        // dotfiles need a listing of their own
+        assert!(preview.request(&dir, stamp, true).is_none());
+        assert_eq!(listed(&mut preview, &woken, &dir, true).unwrap().len(), 5);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn changed_directory_is_listed_again() {
+        let dir = scratch("changed");
+        let (waker, woken) = mpsc::channel();
+        let mut preview = DirPreview::new(waker);
+        let stamp = FileStamp::of(&dir);
+
+        listed(&mut preview, &woken, &dir, false).unwrap();
+        assert!(preview.request(&dir, stamp, false).is_some());
+
+        // the watcher reports the new file; the stamp alone may not have moved
+        fs::write(dir.join("c.txt"), "c").unwrap();
+        preview.invalidate(&dir);
+        assert!(preview.request(&dir, stamp, false).is_none());
+        assert_eq!(listed(&mut preview, &woken, &dir, false).unwrap().last().unwrap(), "c.txt");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unreadable_directory_is_an_error() {
+        let dir = std::env::temp_dir().join(format!("fren-listing-{}-missing", std::process::id()));
+        let (waker, woken) = mpsc::channel();
+        let mut preview = DirPreview::new(waker);
+
+        assert!(listed(&mut preview, &woken, &dir, false).is_err());
+    }
+}
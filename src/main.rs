@@ -1,58 +1,183 @@
+mod animation;
+mod ansi;
 mod app;
+mod archive;
+mod cli;
+mod cache;
 mod config;
+mod data;
+mod encoding;
+mod entry;
 mod event;
+mod exif;
+mod hexdump;
+mod highlight;
+mod jobs;
+mod listing;
+mod markdown;
+mod media;
+mod notify;
+mod pdf;
+mod previewer;
+mod terminal;
+mod textpreview;
 mod theme;
 mod ui;
+mod watch;
 
+use std::fs;
 use std::io;
-use std::time::Duration;
-
-use crossterm::{
-    execute,
-    terminal::{
-        EnterAlternateScreen,
-        LeaveAlternateScreen,
-        disable_raw_mode,
-        enable_raw_mode,
-    },
-};
+use std::os::unix::ffi::OsStrExt;
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Instant;
 
 use ratatui::{Terminal, backend::CrosstermBackend};
 
 use crate::app::App;
+use crate::cli::{Args, Command};
 use crate::config::Config;
+use crate::event::AppEvent;
 use crate::theme::Theme;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // bad arguments are reported while the terminal is still a plain shell
+    let args = match cli::parse(std::env::args_os().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(Command::Version) => {
+            println!("fren {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Ok(Command::ShellInit(shell)) => {
+            println!("{}", cli::shell_init(shell));
+            return Ok(());
+        }
+        Err(message) => {
+            eprintln!("fren: {}\nTry 'fren --help' for more information.", message);
+            process::exit(2);
+        }
+    };
 
-    enable_raw_mode()?;
+    // results for a pipe go to the real stdout, the UI to the terminal
+    let mut picked_out = match &args.choose {
+        Some(chooser) if chooser.output.is_none() => Some(terminal::take_stdout()?),
+        _ => None,
+    };
+
+    terminal::install_panic_hook();
+    terminal::setup()?;
+
+    // whatever happens in there, give the shell its terminal back
+    let result = run(&args);
+    terminal::restore()?;
+    let app = result?;
+
+    // an empty file tells the shell wrapper to stay put
+    if let Some(file) = &args.cwd_file {
+        let dir = if app.cd_on_exit { app.current_dir.as_os_str().as_bytes() } else { &[] };
+        fs::write(file, dir)?;
+    }
+
+    if let Some(chooser) = &args.choose {
+        // quitting without choosing is a cancel
+        let Some(paths) = &app.chosen else {
+            process::exit(1);
+        };
+
+        match (&chooser.output, &mut picked_out) {
+            (Some(file), _) => chooser.write(paths, &mut fs::File::create(file)?)?,
+            (None, Some(out)) => chooser.write(paths, out)?,
+            (None, None) => chooser.write(paths, &mut io::stdout())?,
+        }
+    }
+
+    Ok(())
+}
 
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+fn run(args: &Args) -> Result<App, Box<dyn std::error::Error>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let config = Config::load(args.config.as_deref());
+    let theme = Theme::load(args.theme.as_deref());
 
-    let config = Config::load();
-    let theme = Theme::load();
+    let (events_tx, events) = mpsc::channel();
 
-    let mut app = App::new()?;
+    // the image picker queries the terminal, so input is read only after it's done
+    let mut app = App::new(&config, args, events_tx.clone())?;
     app.load_pinned()?;
 
-    // Main loop
-    loop {
-        if crossterm::event::poll(Duration::from_millis(16))? {
-            if !event::handle_events(&mut app, &mut terminal, &config, &theme)? {
-                break;
+    event::spawn_input(events_tx.clone());
+    terminal::report_worker_panics(events_tx.clone());
+    terminal::spawn_signals(events_tx)?;
+
+    // Main loop: sleep until input, a worker result or a timer, then draw once
+    let mut dirty = true;
+
+    'main: loop {
+        if dirty {
+            app.poll_listing();
+            app.poll_watch();
+            if let Err(e) = app.poll_jobs() {
+                app.notices.error(e.to_string());
             }
+            ui::draw_ui(&mut terminal, &mut app, &config, &theme)?;
+            dirty = false;
         }
 
-        ui::draw_ui(&mut terminal, &mut app, &config, &theme)?;
-    }
+        let first = match app.next_wakeup() {
+            Some(at) => match events.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => {
+                    dirty = true;
+                    None
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match events.recv() {
+                Ok(event) => Some(event),
+                Err(_) => break,
+            },
+        };
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+        // handle everything already queued before drawing again
+        for event in first.into_iter().chain(events.try_iter()) {
+            dirty |= event.redraws();
 
-    Ok(())
+            if event.quits() {
+                break 'main;
+            }
+
+            // the screen was handed to the shell meanwhile, so draw all of it again
+            if event.suspends() {
+                terminal::suspend()?;
+                terminal.clear()?;
+                continue;
+            }
+            if event.resumes() {
+                terminal::setup()?;
+                terminal.clear()?;
+                continue;
+            }
+
+            if let AppEvent::Panic(message) = event {
+                app.notices.error(message);
+                continue;
+            }
+
+            if let AppEvent::Input(input) = event {
+                match event::handle_events(&mut app, input, &mut terminal, &config, &theme) {
+                    Ok(true) => {}
+                    Ok(false) => break 'main,
+                    // whatever an action didn't report itself
+                    Err(e) => app.notices.error(e.to_string()),
+                }
+            }
+        }
+    }
+
+    Ok(app)
 }
@@ -0,0 +1,443 @@
+use std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use lru::LruCache;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::cache::FileStamp;
+use crate::encoding::{self, Encoding};
+use crate::event::AppEvent;
+use crate::highlight;
+use crate::theme::Theme;
+
+// bigger files get the plain text preview, which reads them a window at a time
+pub const MAX_SIZE: u64 = 1024 * 1024;
+const CACHE_SIZE: usize = 16;
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "md" | "markdown"))
+}
+
+/// Render Markdown into styled lines `width` columns wide. Block structure is
+/// handled line by line; paragraphs keep their source line breaks.
+pub fn render(text: &str, width: u16, theme: &Theme) -> Vec<Line<'static>> {
+    let width = width as usize;
+    let mut out = Vec::new();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        //
+        // Fenced code
+        //
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            let lang = trimmed[3..].trim().to_string();
+            i += 1;
+
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                let mut spans = vec![Span::styled("  ", Style::default())];
+                spans.extend(highlight::line(lines[i], &lang, theme));
+                out.push(Line::from(spans));
+                i += 1;
+            }
+
+            i += 1;
+            continue;
+        }
+
+        //
+        // Tables: a header row followed by a |---| separator
+        //
+        if trimmed.starts_with('|') && lines.get(i + 1).is_some_and(|l| is_table_separator(l)) {
+            let mut rows = vec![table_cells(line)];
+            i += 2;
+
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                rows.push(table_cells(lines[i]));
+                i += 1;
+            }
+
+            out.extend(render_table(&rows, theme));
+            continue;
+        }
+
+        i += 1;
+
+        //
+        // Headings
+        //
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            let title = trimmed[level..].trim().trim_end_matches('#').trim();
+            let style = Style::default()
+                .fg(if level <= 2 { theme.focus_border } else { theme.directory })
+                .add_modifier(Modifier::BOLD);
+
+            let heading = Line::from(inline(title, style, theme));
+            let heading_width = heading.width();
+            out.push(heading);
+
+            if level <= 2 {
+                let rule = if level == 1 { "═" } else { "─" };
+                out.push(Line::from(Span::styled(
+                    rule.repeat(heading_width.min(width)),
+                    Style::default().fg(theme.border),
+                )));
+            }
+            continue;
+        }
+
+        //
+        // Horizontal rules
+        //
+        let compact: String = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+        if compact.len() >= 3
+            && ["-", "*", "_"].iter().any(|c| compact.chars().all(|x| x.to_string() == *c))
+        {
+            out.push(Line::from(Span::styled("─".repeat(width), Style::default().fg(theme.border))));
+            continue;
+        }
+
+        //
+        // Block quotes
+        //
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            let mut spans = vec![Span::styled("│ ", Style::default().fg(theme.muted))];
+            spans.extend(inline(
+                quote.trim_start(),
+                Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC),
+                theme,
+            ));
+            out.push(Line::from(spans));
+            continue;
+        }
+
+        //
+        // Lists
+        //
+        let indent = " ".repeat(line.len() - trimmed.len());
+
+        if let Some(item) = ["- ", "* ", "+ "].into_iter().find_map(|m| trimmed.strip_prefix(m)) {
+            let (marker, item) = match item.get(..4) {
+                Some("[ ] ") => ("☐ ", &item[4..]),
+                Some("[x] " | "[X] ") => ("☑ ", &item[4..]),
+                _ => ("• ", item),
+            };
+
+            let mut spans = vec![Span::styled(format!("{}{}", indent, marker), Style::default().fg(theme.focus_border))];
+            spans.extend(inline(item, Style::default(), theme));
+            out.push(Line::from(spans));
+            continue;
+        }
+
+        let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0
+            && let Some(item) = trimmed[digits..].strip_prefix(". ").or(trimmed[digits..].strip_prefix(") "))
+        {
+            let mut spans = vec![Span::styled(
+                format!("{}{}. ", indent, &trimmed[..digits]),
+                Style::default().fg(theme.focus_border),
+            )];
+            spans.extend(inline(item, Style::default(), theme));
+            out.push(Line::from(spans));
+            continue;
+        }
+
+        //
+        // Paragraph text; runs of blank lines collapse to one
+        //
+        if trimmed.is_empty() {
+            if out.last().is_some_and(|l: &Line| l.width() > 0) {
+                out.push(Line::from(""));
+            }
+            continue;
+        }
+
+        out.push(Line::from(inline(trimmed, Style::default(), theme)));
+    }
+
+    out
+}
+
+//
+// Inline markup: emphasis, code spans, links
+//
+fn inline(text: &str, base: Style, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let (mut bold, mut italic, mut strike) = (false, false, false);
+
+    let style = |bold: bool, italic: bool, strike: bool| {
+        let mut style = base;
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if strike {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        style
+    };
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    macro_rules! flush {
+        () => {
+            if !current.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut current), style(bold, italic, strike)));
+            }
+        };
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+
+        match c {
+            '\\' if next.is_some_and(|n| n.is_ascii_punctuation()) => {
+                current.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                let close = chars[i + 1..].iter().position(|&ch| ch == '`');
+                match close {
+                    Some(len) => {
+                        flush!();
+                        let code: String = chars[i + 1..i + 1 + len].iter().collect();
+                        spans.push(Span::styled(code, Style::default().fg(theme.directory)));
+                        i += len + 2;
+                    }
+                    None => {
+                        current.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '_' if next == Some(c) => {
+                flush!();
+                bold = !bold;
+                i += 2;
+            }
+            // underscores inside words are just underscores
+            '_' if prev.is_some_and(char::is_alphanumeric) && next.is_some_and(char::is_alphanumeric) => {
+                current.push(c);
+                i += 1;
+            }
+            '*' | '_' if italic || next.is_some_and(|n| !n.is_whitespace()) => {
+                flush!();
+                italic = !italic;
+                i += 1;
+            }
+            '~' if next == Some('~') => {
+                flush!();
+                strike = !strike;
+                i += 2;
+            }
+            '!' | '[' if let Some((label, url, len)) = link(&chars[i..]) => {
+                flush!();
+
+                let image = c == '!';
+                let label = if image { format!("[image: {}]", label) } else { label };
+
+                spans.push(Span::styled(
+                    label,
+                    style(bold, italic, strike).fg(theme.focus_border).add_modifier(Modifier::UNDERLINED),
+                ));
+                if !image && !url.is_empty() {
+                    spans.push(Span::styled(format!(" ({})", url), Style::default().fg(theme.muted)));
+                }
+                i += len;
+            }
+            // autolinks, as opposed to inline HTML, have no spaces
+            '<' if let Some(len) = chars[i..].iter().position(|&ch| ch == '>')
+                && !chars[i + 1..i + len].iter().any(|ch| ch.is_whitespace())
+                && chars[i + 1..i + len].iter().collect::<String>().contains("://") =>
+            {
+                flush!();
+                let url: String = chars[i + 1..i + len].iter().collect();
+                spans.push(Span::styled(url, Style::default().fg(theme.focus_border).add_modifier(Modifier::UNDERLINED)));
+                i += len + 1;
+            }
+            _ => {
+                current.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    flush!();
+    spans
+}
+
+// `[label](url)` or `![alt](url)`: (label, url, chars consumed)
+fn link(chars: &[char]) -> Option<(String, String, usize)> {
+    let start = if chars.first() == Some(&'!') { 1 } else { 0 };
+    if chars.get(start) != Some(&'[') {
+        return None;
+    }
+
+    let label_end = start + chars[start..].iter().position(|&c| c == ']')?;
+    if chars.get(label_end + 1) != Some(&'(') {
+        return None;
+    }
+    let url_end = label_end + 1 + chars[label_end + 1..].iter().position(|&c| c == ')')?;
+
+    let label = chars[start + 1..label_end].iter().collect();
+    let url = chars[label_end + 2..url_end].iter().collect();
+
+    Some((label, url, url_end + 1))
+}
+
+//
+// Tables
+//
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('|')
+        && line.contains('-')
+        && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim().trim_start_matches('|').trim_end_matches('|');
+    line.split('|').map(|c| c.trim().to_string()).collect()
+}
+
+fn render_table(rows: &[Vec<String>], theme: &Theme) -> Vec<Line<'static>> {
+    // measure the rendered cells, not the markup
+    let cells: Vec<Vec<Vec<Span<'static>>>> = rows
+        .iter()
+        .enumerate()
+        .map(|(n, row)| {
+            let style = if n == 0 {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            row.iter().map(|cell| inline(cell, style, theme)).collect()
+        })
+        .collect();
+
+    let span_width = |spans: &[Span]| spans.iter().map(|s| s.width()).sum::<usize>();
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| cells.iter().filter_map(|r| r.get(c)).map(|s| span_width(s)).max().unwrap_or(0))
+        .collect();
+
+    let border = Style::default().fg(theme.border);
+    let mut out = Vec::new();
+
+    for (n, row) in cells.into_iter().enumerate() {
+        let mut spans = Vec::new();
+
+        for (c, width) in widths.iter().enumerate() {
+            if c > 0 {
+                spans.push(Span::styled(" │ ", border));
+            }
+            let cell = row.get(c).cloned().unwrap_or_default();
+            let pad = width - span_width(&cell);
+            spans.extend(cell);
+            spans.push(Span::raw(" ".repeat(pad)));
+        }
+        out.push(Line::from(spans));
+
+        if n == 0 {
+            let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+            out.push(Line::from(Span::styled(rule.join("─┼─"), border)));
+        }
+    }
+
+    out
+}
+
+//
+// Background rendering
+//
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct MarkdownKey {
+    pub path: PathBuf,
+    pub stamp: FileStamp,
+    pub width: u16,
+}
+
+type Rendered = Result<Arc<Vec<Line<'static>>>, String>;
+
+/// Rendered documents by file version and width, rendered off the draw path.
+pub struct MarkdownPreviews {
+    cache: LruCache<MarkdownKey, Rendered>,
+    pending: HashSet<MarkdownKey>,
+    tx: Sender<(MarkdownKey, Rendered)>,
+    rx: Receiver<(MarkdownKey, Rendered)>,
+    waker: Sender<AppEvent>,
+}
+
+impl MarkdownPreviews {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            pending: HashSet::new(),
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    /// Rendered lines for `key`, or `None` while they're rendered in the background.
+    pub fn request(&mut self, key: &MarkdownKey, encoding: Encoding, theme: &Theme) -> Option<Rendered> {
+        while let Ok((done, lines)) = self.rx.try_recv() {
+            self.pending.remove(&done);
+            self.cache.put(done, lines);
+        }
+
+        if let Some(lines) = self.cache.get(key) {
+            return Some(lines.clone());
+        }
+
+        if self.pending.insert(key.clone()) {
+            let key = key.clone();
+            let theme = theme.clone();
+            let tx = self.tx.clone();
+            let waker = self.waker.clone();
+
+            thread::spawn(move || {
+                let lines = encoding::read(&key.path, encoding)
+                    .map(|text| Arc::new(render(&text, key.width, &theme)))
+                    .map_err(|e| e.to_string());
+                let _ = tx.send((key, lines));
+                let _ = waker.send(AppEvent::Worker);
+            });
+        }
+
+        None
+    }
+
+    pub fn invalidate(&mut self, path: &Path) {
+        let stale: Vec<MarkdownKey> = self.cache.iter().map(|(k, _)| k).filter(|k| k.path == path).cloned().collect();
+
+        for key in stale {
+            self.cache.pop(&key);
+        }
+    }
+}
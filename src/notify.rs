@@ -0,0 +1,101 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+// older messages fall off the log
+const LOG_SIZE: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    // errors stay up long enough to be read
+    fn duration(&self) -> Duration {
+        match self {
+            Self::Info => Duration::from_secs(3),
+            Self::Warning => Duration::from_secs(5),
+            Self::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+pub struct Notice {
+    pub level: Level,
+    pub message: String,
+    pub time: DateTime<Local>,
+}
+
+//
+// Toasts and message log
+//
+/// The latest message shows in the status bar for a few seconds;
+/// all of them stay in the log.
+pub struct Notifications {
+    pub log: VecDeque<Notice>,
+    toast_until: Option<Instant>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            log: VecDeque::new(),
+            toast_until: None,
+        }
+    }
+
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        if self.log.len() == LOG_SIZE {
+            self.log.pop_front();
+        }
+
+        self.log.push_back(Notice {
+            level,
+            message: message.into(),
+            time: Local::now(),
+        });
+        self.toast_until = Some(Instant::now() + level.duration());
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Level::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Level::Error, message);
+    }
+
+    /// The message to show in the status bar, until it times out.
+    pub fn toast(&self) -> Option<&Notice> {
+        self.toast_until
+            .filter(|&until| Instant::now() < until)
+            .and_then(|_| self.log.back())
+    }
+
+    /// When the current toast goes away, so the screen can be redrawn then.
+    pub fn toast_expires_at(&self) -> Option<Instant> {
+        self.toast_until
+    }
+
+    pub fn dismiss(&mut self) {
+        self.toast_until = None;
+    }
+}
@@ -0,0 +1,47 @@
+use std::{path::Path, process::Command};
+
+#[derive(Clone, Default)]
+pub struct PdfInfo {
+    pub pages: Option<usize>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub producer: Option<String>,
+}
+
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+/// Document info from poppler's `pdfinfo`, or `None` when it's missing or fails.
+pub fn info(path: &Path) -> Option<PdfInfo> {
+    let output = Command::new("pdfinfo").arg(path).output().ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut info = PdfInfo::default();
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        match key {
+            "Pages" => info.pages = value.parse().ok(),
+            "Title" => info.title = Some(value.to_string()),
+            "Author" => info.author = Some(value.to_string()),
+            "Producer" => info.producer = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    Some(info)
+}
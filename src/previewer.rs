@@ -0,0 +1,287 @@
+use std::{
+    io::Read,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use ratatui::text::Line;
+
+use crate::cache::FileStamp;
+use crate::config::Previewer;
+use crate::event::AppEvent;
+
+// previewers that take longer than this are killed
+const TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const MAX_OUTPUT: u64 = 1024 * 1024;
+const CACHE_SIZE: usize = 64;
+
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct PreviewerKey {
+    pub path: PathBuf,
+    pub stamp: FileStamp,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Clone)]
+pub enum Output {
+    Text(Vec<Line<'static>>),
+    Image(PathBuf),
+    Failed(String),
+}
+
+//
+// Matching
+//
+/// First previewer whose pattern matches the file. Patterns with a `/` are
+/// MIME types, anything else is a glob on the file name.
+pub fn find<'a>(previewers: &'a [Previewer], path: &Path) -> Option<&'a Previewer> {
+    let name = path.file_name()?.to_str()?;
+    let mut mime = None;
+
+    previewers.iter().find(|p| {
+        if p.pattern.contains('/') {
+            let mime = *mime.get_or_insert_with(|| mime_type(path));
+            glob_match(&p.pattern, mime)
+        } else {
+            glob_match(&p.pattern, name)
+        }
+    })
+}
+
+// `*` and `?` wildcards
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    match ext.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "rs" => "text/x-rust",
+        "c" | "h" => "text/x-c",
+        "py" => "text/x-python",
+        "sh" => "text/x-shellscript",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        "zip" => "application/zip",
+        "tar" => "application/x-tar",
+        "gz" | "tgz" => "application/gzip",
+        _ if crate::hexdump::looks_binary(path) => "application/octet-stream",
+        _ => "text/plain",
+    }
+}
+
+//
+// Running
+//
+/// Previewer output by file and size, with at most one command running at a time.
+pub struct ExternalPreviews {
+    cache: LruCache<PreviewerKey, Output>,
+    running: Option<(PreviewerKey, Arc<AtomicBool>)>,
+    tx: Sender<(PreviewerKey, Output)>,
+    rx: Receiver<(PreviewerKey, Output)>,
+    waker: Sender<AppEvent>,
+}
+
+impl ExternalPreviews {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            running: None,
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    /// Cached output for `key`, or `None` while the command is still running.
+    /// Asking for a different key kills whatever was running before.
+    pub fn request(&mut self, key: &PreviewerKey, command: &str) -> Option<Output> {
+        while let Ok((done, output)) = self.rx.try_recv() {
+            if self.running.as_ref().is_some_and(|(k, _)| *k == done) {
+                self.running = None;
+            }
+            self.cache.put(done, output);
+        }
+
+        if let Some(output) = self.cache.get(key) {
+            return Some(output.clone());
+        }
+
+        if self.running.as_ref().is_some_and(|(k, _)| k == key) {
+            return None;
+        }
+
+        self.cancel();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        let worker_key = key.clone();
+        let command = command.to_string();
+        let tx = self.tx.clone();
+        let waker = self.waker.clone();
+
+        thread::spawn(move || {
+            if let Some(output) = run(&command, &worker_key, &worker_cancel) {
+                let _ = tx.send((worker_key, output));
+                let _ = waker.send(AppEvent::Worker);
+            }
+        });
+
+        self.running = Some((key.clone(), cancel));
+        None
+    }
+
+    pub fn invalidate(&mut self, path: &Path) {
+        let stale: Vec<PreviewerKey> = self.cache.iter().map(|(k, _)| k).filter(|k| k.path == path).cloned().collect();
+
+        for key in stale {
+            self.cache.pop(&key);
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some((_, cancel)) = self.running.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// `None` when cancelled, so nothing half-finished lands in the cache
+fn run(command: &str, key: &PreviewerKey, cancel: &AtomicBool) -> Option<Output> {
+    // exec so that killing the child kills the previewer, not just the shell
+    let spawned = Command::new("sh")
+        .arg("-c")
+        .arg(format!("exec {} \"$@\"", command))
+        .arg("sh")
+        .arg(&key.path)
+        .arg(key.width.to_string())
+        .arg(key.height.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => return Some(Output::Failed(format!("{}: {}", command, e))),
+    };
+
+    let stdout = child.stdout.take()?;
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.take(MAX_OUTPUT).read_to_end(&mut buf);
+        let _ = tx.send(buf);
+    });
+
+    let started = Instant::now();
+
+    let buf = loop {
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+
+        if started.elapsed() > TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Some(Output::Failed(format!("{}: timed out", command)));
+        }
+
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(buf) => break buf,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break Vec::new(),
+        }
+    };
+
+    // output was capped, don't wait on a writer that will never finish
+    if let Ok(None) = child.try_wait() {
+        let _ = child.kill();
+    }
+    let status = child.wait().ok();
+
+    let text = String::from_utf8_lossy(&buf);
+    let trimmed = text.trim();
+
+    // a single line naming an image file means "show this image"
+    if !trimmed.is_empty() && !trimmed.contains('\n') {
+        let image = PathBuf::from(trimmed);
+        if image.is_file() && image::ImageFormat::from_path(&image).is_ok() {
+            return Some(Output::Image(image));
+        }
+    }
+
+    if trimmed.is_empty() && !status.is_some_and(|s| s.success()) {
+        return Some(Output::Failed(format!("{}: exited with an error", command)));
+    }
+
+    Some(Output::Text(crate::ansi::to_lines(&text)))
+}
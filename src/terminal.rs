@@ -0,0 +1,146 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    os::fd::{AsRawFd, FromRawFd},
+    panic,
+    path::PathBuf,
+    sync::{OnceLock, mpsc::Sender},
+    thread::{self, ThreadId},
+};
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use signal_hook::{
+    consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP},
+    iterator::Signals,
+    low_level,
+};
+
+use crate::event::AppEvent;
+
+//
+// Raw mode and the alternate screen
+//
+pub fn setup() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+/// Give the shell its terminal back. Safe to call more than once.
+pub fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show)
+}
+
+/// Point stdout at the terminal and hand back the original, so the UI can
+/// still draw when stdout is a pipe that should only get results.
+pub fn take_stdout() -> io::Result<File> {
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+
+    unsafe {
+        let original = libc::dup(libc::STDOUT_FILENO);
+        if original < 0 || libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(File::from_raw_fd(original))
+    }
+}
+
+/// Stop like Ctrl-Z does outside raw mode, and take the terminal back on `fg`.
+pub fn suspend() -> io::Result<()> {
+    restore()?;
+    low_level::emulate_default_handler(SIGTSTP)?;
+    setup()
+}
+
+//
+// Signals
+//
+/// Forward termination and job control signals to the main loop.
+pub fn spawn_signals(tx: Sender<AppEvent>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGTERM, SIGINT, SIGHUP, SIGTSTP, SIGCONT])?;
+
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if tx.send(AppEvent::Signal(signal)).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+//
+// Panics
+//
+// the thread that owns the terminal, and where worker panics are reported
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+static PANIC_WAKER: OnceLock<Sender<AppEvent>> = OnceLock::new();
+
+/// Keep a copy of every panic in the crash log. Only a panic on the main
+/// thread, which is the one that ends fren, restores the terminal and prints
+/// the message; the UI keeps running through a worker's, reported by
+/// `report_worker_panics`. Call from the main thread.
+pub fn install_panic_hook() {
+    let _ = MAIN_THREAD.set(thread::current().id());
+    let default = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let on_main = MAIN_THREAD.get() == Some(&thread::current().id());
+
+        if on_main {
+            let _ = restore();
+        }
+
+        let logged = crash_log_path().and_then(|path| {
+            fs::create_dir_all(path.parent()?).ok()?;
+            let mut file = OpenOptions::new().create(true).append(true).open(&path).ok()?;
+
+            writeln!(
+                file,
+                "--- {} fren {}\n{}\n{}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                env!("CARGO_PKG_VERSION"),
+                info,
+                std::backtrace::Backtrace::force_capture(),
+            )
+            .ok()?;
+
+            Some(path)
+        });
+
+        // printing would scribble over the UI
+        if !on_main {
+            if let Some(waker) = PANIC_WAKER.get() {
+                let message = match &logged {
+                    Some(path) => format!("A background task crashed, details were saved to {}", path.display()),
+                    None => format!("A background task crashed: {}", info),
+                };
+                let _ = waker.send(AppEvent::Panic(message));
+            }
+            return;
+        }
+
+        default(info);
+
+        if let Some(path) = logged {
+            eprintln!("fren crashed, details were saved to {}", path.display());
+        }
+    }));
+}
+
+/// Send worker panics to the main loop, to show as errors.
+pub fn report_worker_panics(tx: Sender<AppEvent>) {
+    let _ = PANIC_WAKER.set(tx);
+}
+
+// ~/.local/state/fren/crash.log on Linux
+fn crash_log_path() -> Option<PathBuf> {
+    let dir = dirs::state_dir().or_else(dirs::data_local_dir)?;
+    Some(dir.join("fren").join("crash.log"))
+}
@@ -0,0 +1,515 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use lru::LruCache;
+
+use crate::cache::FileStamp;
+use crate::encoding::{self, Encoding};
+use crate::event::AppEvent;
+
+// windows start a step apart and span two steps, so neighbours overlap by half
+const STEP: u64 = 128 * 1024;
+const CACHE_SIZE: usize = 32;
+
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct TextKey {
+    pub path: PathBuf,
+    pub stamp: FileStamp,
+    pub window: usize,
+}
+
+/// Whole lines decoded from one window of a file. A line running through a
+/// whole step without a newline is cut at the step, so it shows in pieces.
+pub struct TextWindow {
+    pub lines: Vec<String>,
+    /// Line where the next window begins; `None` when this one reaches the end.
+    pub next: Option<usize>,
+    // byte range the lines came from, for estimating the position in the file
+    pub start: u64,
+    pub len: u64,
+}
+
+/// The window that ends the file and still spans two steps.
+pub fn last_window(size: u64) -> usize {
+    (size.saturating_sub(1) / STEP).saturating_sub(1) as usize
+}
+
+//
+// Background reading
+//
+pub struct TextPreviews {
+    cache: LruCache<TextKey, Result<Arc<TextWindow>, String>>,
+    pending: HashSet<TextKey>,
+    tx: Sender<(TextKey, Result<TextWindow, String>)>,
+    rx: Receiver<(TextKey, Result<TextWindow, String>)>,
+    waker: Sender<AppEvent>,
+}
+
+impl TextPreviews {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        Self {
+            cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+            pending: HashSet::new(),
+            tx,
+            rx,
+            waker,
+        }
+    }
+
+    /// Cached window for `key`, or `None` while it's read in the background.
+    pub fn request(&mut self, key: &TextKey, encoding: Encoding) -> Option<Result<Arc<TextWindow>, String>> {
+        while let Ok((done, window)) = self.rx.try_recv() {
+            self.pending.remove(&done);
+            self.cache.put(done, window.map(Arc::new));
+        }
+
+        if let Some(window) = self.cache.get(key) {
+            return Some(window.clone());
+        }
+
+        if self.pending.insert(key.clone()) {
+            let key = key.clone();
+            let tx = self.tx.clone();
+            let waker = self.waker.clone();
+
+            thread::spawn(move || {
+                let window = read_window(&key, encoding).map_err(|e| e.to_string());
+                let _ = tx.send((key, window));
+                let _ = waker.send(AppEvent::Worker);
+            });
+        }
+
+        None
+    }
+
+    /// The window that holds `scroll`. Near either end it hands over to the
+    /// neighbouring window once that has loaded, moving `window` and `scroll`
+    /// along, so big files scroll without ever being read whole. `None` while loading.
+    pub fn view(
+        &mut self,
+        path: &Path,
+        encoding: Encoding,
+        window: &mut usize,
+        scroll: &mut usize,
+        visible: usize,
+    ) -> Option<Result<Arc<TextWindow>, String>> {
+        let stamp = FileStamp::of(path);
+        let key = |window| TextKey {
+            path: path.to_path_buf(),
+            stamp,
+            window,
+        };
+
+        // jumping to the bottom starts from the last window
+        if *scroll == usize::MAX {
+            *window = last_window(stamp.size);
+        }
+
+        let current = match self.request(&key(*window), encoding)? {
+            Ok(current) => current,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let previous = window.checked_sub(1);
+
+        // the next window takes over once the top line is in it and it shows more
+        if let Some(next) = current.next
+            && *scroll >= next
+            && scroll.saturating_add(visible) > current.lines.len()
+            && let Some(Ok(after)) = self.request(&key(*window + 1), encoding)
+        {
+            *window += 1;
+            *scroll = scroll.saturating_sub(next);
+            return Some(Ok(after));
+        }
+
+        // only step back when the view fits there, so the two moves can't ping-pong
+        if let Some(previous) = previous
+            && *scroll < visible
+            && let Some(Ok(before)) = self.request(&key(previous), encoding)
+            && let Some(back) = before.next
+            && *scroll + back + visible <= before.lines.len()
+        {
+            *window = previous;
+            *scroll += back;
+            return Some(Ok(before));
+        }
+
+        // read the neighbours ahead of time
+        if current.next.is_some() {
+            self.request(&key(*window + 1), encoding);
+        }
+        if let Some(previous) = previous {
+            self.request(&key(previous), encoding);
+        }
+
+        Some(Ok(current))
+    }
+
+    /// Scroll `lines` up from the top of `window`, into the window before once
+    /// it's loaded. False when there's no window before to go to.
+    pub fn scroll_up(&mut self, path: &Path, window: &mut usize, scroll: &mut usize, lines: usize) -> bool {
+        let Some(previous) = window.checked_sub(1) else {
+            return false;
+        };
+
+        let key = TextKey {
+            path: path.to_path_buf(),
+            stamp: FileStamp::of(path),
+            window: previous,
+        };
+
+        let Some(Ok(before)) = self.cache.get(&key) else {
+            return false;
+        };
+        let Some(back) = before.next else {
+            return false;
+        };
+
+        *window = previous;
+        *scroll = (*scroll + back).saturating_sub(lines);
+        true
+    }
+
+    pub fn invalidate(&mut self, path: &Path) {
+        let stale: Vec<TextKey> = self.cache.iter().map(|(k, _)| k).filter(|k| k.path == path).cloned().collect();
+
+        for key in stale {
+            self.cache.pop(&key);
+        }
+    }
+}
+
+// whole lines from [window * STEP, window * STEP + 2 * STEP)
+fn read_window(key: &TextKey, encoding: Encoding) -> io::Result<TextWindow> {
+    let start = key.window as u64 * STEP;
+    let step = STEP as usize;
+
+    let mut file = File::open(&key.path)?;
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut buf = Vec::new();
+    file.take(2 * STEP).read_to_end(&mut buf)?;
+    let at_eof = start + buf.len() as u64 >= key.stamp.size;
+
+    // where each newline ends; UTF-16 newlines are two aligned bytes
+    let unit = match encoding {
+        Encoding::Utf16Le | Encoding::Utf16Be => 2,
+        _ => 1,
+    };
+    let ends: Vec<usize> = (0..buf.len().saturating_sub(unit - 1))
+        .step_by(unit)
+        .filter(|&i| match encoding {
+            Encoding::Utf16Le => buf[i] == b'\n' && buf[i + 1] == 0,
+            Encoding::Utf16Be => buf[i] == 0 && buf[i + 1] == b'\n',
+            _ => buf[i] == b'\n',
+        })
+        .map(|i| i + unit)
+        .collect();
+
+    // a window starts with the first line that begins within its first step,
+    // or right at the step when a line runs through all of it; the window
+    // before finds the same place one step into its own read
+    let first_line = |from: usize| {
+        ends.iter()
+            .copied()
+            .find(|&e| e > from && e <= from + step)
+            .unwrap_or(from)
+    };
+
+    let from = match key.window {
+        0 => 0,
+        _ => first_line(0),
+    };
+    let next_from = (!at_eof).then(|| first_line(step));
+
+    let to = match next_from {
+        Some(next_from) => ends.last().copied().unwrap_or(0).max(next_from),
+        None => buf.len(),
+    };
+
+    // lines end after each newline, and where the next window starts
+    let mut cuts: Vec<usize> = ends.iter().copied().filter(|&e| e > from && e <= to).collect();
+    if let Some(next_from) = next_from
+        && next_from > from
+        && !cuts.contains(&next_from)
+    {
+        let at = cuts.partition_point(|&c| c < next_from);
+        cuts.insert(at, next_from);
+    }
+    // and at the end of the file without one
+    if cuts.last().copied().unwrap_or(from) < to {
+        cuts.push(to);
+    }
+
+    let next = next_from.map(|next_from| cuts.iter().filter(|&&c| c <= next_from).count());
+
+    let mut lines = Vec::with_capacity(cuts.len());
+    let mut begin = from;
+    for &cut in &cuts {
+        let text = encoding::decode(&buf[begin..cut], encoding);
+        lines.push(encoding::show_controls(text.lines().next().unwrap_or("")));
+        begin = cut;
+    }
+
+    Ok(TextWindow {
+        lines,
+        next,
+        start: start + from as u64,
+        len: (to - from) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `lines` lines of `width` bytes each, newline included, numbered from 0
+    fn numbered(name: &str, lines: usize, width: usize) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("fren-text-{}-{}", std::process::id(), name));
+        let text: String = (0..lines).map(|i| format!("{:0w$}\n", i, w = width - 1)).collect();
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn key(path: &Path, window: usize) -> TextKey {
+        TextKey {
+            path: path.to_path_buf(),
+            stamp: FileStamp::of(path),
+            window,
+        }
+    }
+
+    // read every window up front, so handovers don't wait on a thread
+    fn preloaded(path: &Path) -> TextPreviews {
+        let (waker, _) = mpsc::channel();
+        let mut previews = TextPreviews::new(waker);
+
+        for window in 0..=last_window(FileStamp::of(path).size) {
+            let key = key(path, window);
+            let read = read_window(&key, Encoding::Utf8).map(Arc::new).map_err(|e| e.to_string());
+            previews.cache.put(key, read);
+        }
+
+        previews
+    }
+
+    fn window(path: &Path, window: usize) -> TextWindow {
+        read_window(&key(path, window), Encoding::Utf8).unwrap()
+    }
+
+    // each window's `next` line is where the following one starts
+    fn assert_windows_line_up(path: &Path, lines: usize) {
+        let last = last_window(FileStamp::of(path).size);
+        let first = window(path, 0);
+        assert_eq!(first.lines[0].parse::<usize>().unwrap(), 0);
+
+        for w in 0..last {
+            let (this, after) = (window(path, w), window(path, w + 1));
+            let next = this.next.expect("only the last window reaches the end");
+            assert_eq!(this.lines[next], after.lines[0], "window {} hands over to the wrong line", w);
+        }
+
+        let end = window(path, last);
+        assert!(end.next.is_none());
+        assert_eq!(end.lines.last().unwrap().parse::<usize>().unwrap(), lines - 1);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // like the preview: show `scroll` and keep it where the window can go
+    fn show(previews: &mut TextPreviews, path: &Path, at: &mut usize, scroll: &mut usize, visible: usize) -> String {
+        let shown = previews.view(path, Encoding::Utf8, at, scroll, visible).unwrap().unwrap();
+        let scrollable = match shown.next {
+            Some(_) => shown.lines.len() + visible - 1,
+            None => shown.lines.len(),
+        };
+        *scroll = (*scroll).min(scrollable.saturating_sub(visible));
+        shown.lines[*scroll].clone()
+    }
+
+    // the line on top at each step of scrolling one line at a time to the
+    // bottom, then back up to the top
+    fn scroll_through(path: &Path, visible: usize) -> (Vec<String>, Vec<String>) {
+        let mut previews = preloaded(path);
+        let (mut at, mut scroll) = (0, 0);
+
+        let mut down = vec![show(&mut previews, path, &mut at, &mut scroll, visible)];
+        loop {
+            let was = (at, scroll);
+            scroll += 1;
+            let top = show(&mut previews, path, &mut at, &mut scroll, visible);
+            if (at, scroll) == was {
+                break;
+            }
+            down.push(top);
+        }
+
+        let mut up = vec![down.last().unwrap().clone()];
+        while (at, scroll) != (0, 0) {
+            if scroll == 0 {
+                assert!(previews.scroll_up(path, &mut at, &mut scroll, 1));
+            } else {
+                scroll -= 1;
+            }
+            up.push(show(&mut previews, path, &mut at, &mut scroll, visible));
+        }
+
+        (down, up)
+    }
+
+    #[test]
+    fn long_lines_can_all_be_reached() {
+        let path = std::env::temp_dir().join(format!("fren-text-{}-long", std::process::id()));
+        let lines: Vec<String> = ["a", "b", "c"].iter().map(|c| c.repeat(100_000)).collect();
+        std::fs::write(&path, lines.iter().map(|l| format!("{}\n", l)).collect::<String>()).unwrap();
+
+        // opening shows the first line, not the window that ends the file
+        let mut previews = preloaded(&path);
+        let (mut at, mut scroll) = (0, 0);
+        assert_eq!(show(&mut previews, &path, &mut at, &mut scroll, 30), lines[0]);
+        assert_eq!(at, 0);
+
+        let (down, mut up) = scroll_through(&path, 30);
+        assert_eq!(down, lines);
+        up.reverse();
+        assert_eq!(up, lines);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn short_lines_scroll_one_by_one() {
+        let lines = 3 * STEP as usize / 37 + 5;
+        let path = numbered("one-by-one", lines, 37);
+
+        let (down, up) = scroll_through(&path, 30);
+        let tops: Vec<usize> = down.iter().map(|l| l.parse().unwrap()).collect();
+        assert_eq!(tops, (0..=lines - 30).collect::<Vec<_>>());
+        assert_eq!(up.len(), down.len());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn file_without_newlines_is_cut_at_steps() {
+        let path = std::env::temp_dir().join(format!("fren-text-{}-no-newline", std::process::id()));
+        let text: String = (0..600_000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+        std::fs::write(&path, &text).unwrap();
+
+        for w in 0..=last_window(text.len() as u64) {
+            let shown = window(&path, w);
+            assert!(shown.lines.iter().all(|l| !l.is_empty() && l.len() <= 2 * STEP as usize));
+        }
+
+        let (down, mut up) = scroll_through(&path, 30);
+        assert_eq!(down.concat(), text);
+        up.reverse();
+        assert_eq!(up, down);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn small_file_is_one_window() {
+        let path = numbered("small", 100, 10);
+        let only = window(&path, 0);
+
+        assert_eq!(last_window(FileStamp::of(&path).size), 0);
+        assert_eq!(only.lines.len(), 100);
+        assert!(only.next.is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn windows_line_up() {
+        // lines that straddle every step boundary
+        let lines = 4 * STEP as usize / 37 + 5;
+        assert_windows_line_up(&numbered("straddle", lines, 37), lines);
+    }
+
+    #[test]
+    fn windows_line_up_at_exact_steps() {
+        // lines end exactly on step boundaries and the file on a step multiple
+        let lines = 3 * STEP as usize / 16;
+        let path = numbered("exact", lines, 16);
+
+        assert_eq!(FileStamp::of(&path).size, 3 * STEP);
+        assert_eq!(last_window(3 * STEP), 1);
+        assert_windows_line_up(&path, lines);
+
+        let path = numbered("exact-two", 2 * STEP as usize / 16, 16);
+        assert_eq!(last_window(2 * STEP), 0);
+        assert!(window(&path, 0).next.is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn bottom_starts_from_the_last_window() {
+        let lines = 5 * STEP as usize / 20;
+        let path = numbered("bottom", lines, 20);
+        let mut previews = preloaded(&path);
+
+        let (mut at, mut scroll) = (0, usize::MAX);
+        let shown = previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, 30).unwrap().unwrap();
+
+        assert_eq!(at, last_window(FileStamp::of(&path).size));
+        assert!(shown.next.is_none());
+        assert_eq!(shown.lines.last().unwrap().parse::<usize>().unwrap(), lines - 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn scrolling_crosses_window_boundaries() {
+        let lines = 4 * STEP as usize / 20;
+        let path = numbered("scroll", lines, 20);
+        let mut previews = preloaded(&path);
+        let visible = 30;
+
+        let (mut at, mut scroll) = (0, 0);
+        let first = previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, visible).unwrap().unwrap();
+        assert_eq!(at, 0);
+
+        // the bottom of the view runs past window 0, so window 1 takes over
+        let top = first.lines.len() - visible + 1;
+        scroll = top;
+        let shown = previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, visible).unwrap().unwrap();
+        assert_eq!(at, 1);
+        assert_eq!(shown.lines[scroll], first.lines[top]);
+
+        // back at the top of window 1, window 0 takes over with the same line on top
+        let line = shown.lines[0].clone();
+        scroll = 0;
+        let shown = previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, visible).unwrap().unwrap();
+        assert_eq!(at, 0);
+        assert_eq!(shown.lines[scroll], line);
+
+        // a view that fits in window 1 stays there
+        let (mut at, mut scroll) = (1, visible);
+        previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, visible).unwrap().unwrap();
+        assert_eq!((at, scroll), (1, visible));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn windows_are_read_in_the_background() {
+        let path = numbered("background", 10, 10);
+        let (waker, woken) = mpsc::channel();
+        let mut previews = TextPreviews::new(waker);
+
+        let (mut at, mut scroll) = (0, 0);
+        assert!(previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, 5).is_none());
+
+        woken.recv().unwrap();
+        assert_eq!(previews.view(&path, Encoding::Utf8, &mut at, &mut scroll, 5).unwrap().unwrap().lines.len(), 10);
+        let _ = std::fs::remove_file(&path);
+    }
+}
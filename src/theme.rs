@@ -1,6 +1,11 @@
 use ratatui::style::Color;
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
+#[derive(Clone)]
 pub struct Theme {
     pub background: Color,
     pub foreground: Color,
@@ -10,6 +15,8 @@ pub struct Theme {
     pub status_bg: Color,
     pub status_fg: Color,
     pub muted: Color,
+    pub error: Color,
+    pub warning: Color,
 }
 
 impl Theme {
@@ -33,6 +40,8 @@ impl Theme {
                 border = "#26323d"
                 focus_border = "#00d4ff"
                 muted = "#5c6a72"
+                error = "#ff6b6b"
+                warning = "#f5c26b"
 
                 directory = "#4fc3f7"
 
@@ -45,14 +54,19 @@ impl Theme {
         }
     }
 
-    /// Load theme from ~/.config/fren/theme.toml
-    pub fn load() -> Self {
-        Self::ensure_config_exists();
-
-        let path = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("fren")
-            .join("theme.toml");
+    /// Load theme from ~/.config/fren/theme.toml, or from `file` when given
+    pub fn load(file: Option<&Path>) -> Self {
+        let path = match file {
+            Some(file) => file.to_path_buf(),
+            None => {
+                Self::ensure_config_exists();
+
+                dirs::config_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join("fren")
+                    .join("theme.toml")
+            }
+        };
 
         let mut theme = Self::default();
 
@@ -83,6 +97,12 @@ impl Theme {
             if let Some(v) = values.get("muted") {
                 theme.muted = parse_color(v);
             }
+            if let Some(v) = values.get("error") {
+                theme.error = parse_color(v);
+            }
+            if let Some(v) = values.get("warning") {
+                theme.warning = parse_color(v);
+            }
         }
 
         theme
@@ -100,6 +120,8 @@ impl Default for Theme {
             status_bg: Color::DarkGray,
             status_fg: Color::White,
             muted: Color::Blue,
+            error: Color::Red,
+            warning: Color::Yellow,
         }
     }
 }
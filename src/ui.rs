@@ -1,15 +1,18 @@
 use std::{
-    io, os::unix::fs::PermissionsExt,
+    io,
     path::PathBuf,
 };
 
 use ratatui::{
     Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect, Alignment},
+    layout::{Constraint, Direction, Layout, Margin, Rect, Alignment},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
 };
 
 use ratatui_image::{Image};
@@ -20,8 +23,17 @@ use crate::app::{App, AppMode, ClipboardMode, Focus, InputAction};
 use crate::config::Config;
 use crate::theme::Theme;
 use crate::app::ImageKey;
-use crate::app::quantize;
-use crate::app::PreviewJob;
+use crate::app::{list_archive, quantize};
+use crate::app::{PreviewJob, PreviewKind};
+use crate::archive::{self, ArchiveEntry, ArchiveKind};
+use crate::data::{self, DataKey, Format};
+use crate::encoding::TextInfo;
+use crate::exif::{self, Exif};
+use crate::markdown::{self, MarkdownKey};
+use crate::media::{self, MediaInfo};
+use crate::notify::{Level, Notifications};
+use crate::pdf::{self, PdfInfo};
+use crate::previewer::{self, Output, PreviewerKey};
 
 //
 // Human readable size
@@ -101,7 +113,10 @@ pub fn draw_ui(
                     .fg(theme.focus_border)
                     .add_modifier(Modifier::BOLD),
             ),
-            Span::raw(app.current_dir.display().to_string()),
+            Span::raw(match &app.archive {
+                Some(view) => view.display_path().display().to_string(),
+                None => app.current_dir.display().to_string(),
+            }),
         ]))
         .style(Style::default().bg(theme.background).fg(theme.foreground));
 
@@ -195,6 +210,7 @@ pub fn draw_ui(
             match mode {
                 ClipboardMode::Copy => format!("Copy: {}", name),
                 ClipboardMode::Cut => format!("Cut: {}", name),
+                ClipboardMode::Extract { .. } => format!("Extract: {}", name),
             }
         } else {
             "Empty".to_string()
@@ -214,16 +230,33 @@ pub fn draw_ui(
         //
         let files_focused = app.focus == Focus::Files;
 
-        let items: Vec<ListItem> = app
-            .entries
+        let items: Vec<ListItem> = if let Some(view) = &app.archive {
+            view.entries
+                .iter()
+                .map(|e| {
+                    let icon = App::icon_for_kind(std::path::Path::new(&e.path), e.is_dir, app.icon_mode);
+
+                    let color = if !files_focused {
+                        theme.muted
+                    } else if e.is_dir {
+                        theme.directory
+                    } else {
+                        theme.foreground
+                    };
+
+                    ListItem::new(Line::from(vec![
+                        Span::styled(icon, Style::default().fg(theme.muted)),
+                        Span::styled(e.name().to_string(), Style::default().fg(color)),
+                    ]))
+                })
+                .collect()
+        } else {
+            app.entries
             .iter()
             .map(|e| {
-                let path = e.path();
-                let name = e.file_name().to_string_lossy().into_owned();
+                let icon = App::icon(e.kind, app.icon_mode);
 
-                let icon = App::icon_for(&path, app.icon_mode);
-
-                let base_color = if path.is_dir() {
+                let base_color = if e.is_dir {
                     theme.directory
                 } else {
                     theme.foreground
@@ -235,23 +268,37 @@ pub fn draw_ui(
                     theme.muted
                 };
 
+                let name_style = if app.marked.contains(&e.path) {
+                    Style::default()
+                        .fg(theme.focus_border)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(color)
+                };
+
                 let line = Line::from(vec![
                     Span::styled(icon, Style::default().fg(theme.muted)),
-                    Span::styled(name, Style::default().fg(color)),
+                    Span::styled(e.name.clone(), name_style),
                 ]);
 
                 ListItem::new(line)
             })
-            .collect();
+            .collect()
+        };
 
         let mut state = ListState::default();
         state.select(Some(app.selected));
 
+        let files_title = match app.loader.progress() {
+            Some(count) => format!(" Files — loading {} entries… ", count),
+            None => " Files ".to_string(),
+        };
+
         let list = List::new(items)
             .block(
                 Block::default()
                     .title(Span::styled(
-                        " Files ",
+                        files_title,
                         Style::default()
                             .fg(if files_focused {
                                 theme.focus_border
@@ -287,119 +334,153 @@ pub fn draw_ui(
 
         let metadata_area = middle_chunks[1];
 
-        let metadata_lines: Vec<Line> = if let Some(entry) = app.entries.get(app.selected) {
-
-            let path = entry.path().to_path_buf();
-
-            match std::fs::symlink_metadata(&path) {
-                Ok(meta) => {
-                    // -------- File name (OWNED) --------
-                    let file_name: String = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("Unknown")
-                        .to_string();
-
-                    // -------- File type (OWNED) --------
-                    let file_type: String = if meta.file_type().is_symlink() {
-                        "Symlink".to_string()
-                    } else if meta.is_dir() {
-                        "Directory".to_string()
-                    } else if meta.is_file() {
-                        "File".to_string()
-                    } else {
-                        "Other".to_string()
-                    };
-                    //---------- Resolution of img -----------
-                    let resolution_line = if meta.is_file() {
-                        if let Some((w, h)) = crate::app::get_dimensions(&path) {
-                            Some(Line::from(vec![
-                                Span::styled("Resolution ", Style::default().fg(theme.muted)),
-                                Span::raw(format!("{}x{}", w, h)),
-                            ]))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
-                    // -------- Size (OWNED) --------
-                    let size: String = if meta.is_file() {
-                        format_size(meta.len())
-                    } else if meta.is_dir() {
-                        if let Ok(entries) = std::fs::read_dir(&path) {
-                            let total: u64 = entries
-                                .flatten()
-                                .filter_map(|e| e.metadata().ok())
-                                .filter(|m| m.is_file())
-                                .map(|m| m.len())
-                                .sum();
-
-                            format_size(total)
-                        } else {
-                            "-".to_string()
-                        }
-                    } else {
-                        "-".to_string()
-                    };
+        let metadata_lines: Vec<Line> = if let Some(view) = &app.archive {
+            match view.entries.get(app.selected) {
+                Some(entry) => archive_metadata_lines(view.archive.as_path(), entry, theme),
+                None => vec![Line::from(Span::styled(
+                    "Empty archive directory",
+                    Style::default().fg(theme.muted),
+                ))],
+            }
+        } else if let Some(entry) = app.entries.get(app.selected) {
+            let path = entry.path.clone();
+            let is_file = entry.is_file;
+
+            // -------- File type (OWNED) --------
+            let file_type: String = if entry.is_symlink() {
+                "Symlink".to_string()
+            } else if entry.is_dir {
+                "Directory".to_string()
+            } else if entry.is_file {
+                "File".to_string()
+            } else {
+                "Other".to_string()
+            };
+            let link_line = entry.link_target.as_ref().map(|target| {
+                Line::from(vec![
+                    Span::styled("Target    ", Style::default().fg(theme.muted)),
+                    Span::raw(target.display().to_string()),
+                ])
+            });
+
+            // -------- Modified time (OWNED) --------
+            let modified: String = entry
+                .modified
+                .map(|time| {
+                    let datetime: chrono::DateTime<chrono::Local> = time.into();
+                    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+                })
+                .unwrap_or_else(|| "Unknown".to_string());
+
+            // -------- Permissions (OWNED) --------
+            let perms: String = format_permissions(entry.mode);
+            let octal: String = format!("{:o}", entry.mode & 0o777);
+
+            let file_name = entry.name.clone();
+            let owner = entry.owner.clone();
+            let path_string: String = path.display().to_string();
+
+            // -------- Size (OWNED) --------
+            let size: String = if is_file {
+                format_size(entry.size)
+            } else if entry.is_dir {
+                app.dir_size_for(&path).map_or_else(|| "…".to_string(), format_size)
+            } else {
+                "-".to_string()
+            };
 
-                    // -------- Modified time (OWNED) --------
-                    let modified: String = meta
-                        .modified()
-                        .ok()
-                        .and_then(|time| {
-                            let datetime: chrono::DateTime<chrono::Local> = time.into();
-                            Some(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
-                        })
-                        .unwrap_or_else(|| "Unknown".to_string());
-
-                    // -------- Permissions (OWNED) --------
-                    let mode = meta.permissions().mode();
-                    let perms: String = format_permissions(mode);
-                    let octal: String = format!("{:o}", mode & 0o777);
-
-                    let path_string: String = path.display().to_string();
-
-                    let mut lines = vec![
-                        Line::from(vec![
-                            Span::styled("Name      ", Style::default().fg(theme.muted)),
-                            Span::styled(file_name, Style::default().fg(theme.foreground)),
-                        ]),
-                        Line::from(vec![
-                            Span::styled("Type      ", Style::default().fg(theme.muted)),
-                            Span::raw(file_type),
-                        ]),
-                        Line::from(vec![
-                            Span::styled("Size      ", Style::default().fg(theme.muted)),
-                            Span::raw(size),
-                        ]),
-                        Line::from(vec![
-                            Span::styled("Perms     ", Style::default().fg(theme.muted)),
-                            Span::raw(format!("{} ({})", perms, octal)),
-                        ]),
-                        Line::from(vec![
-                            Span::styled("Modified  ", Style::default().fg(theme.muted)),
-                            Span::raw(modified),
-                        ]),
-                        Line::from(""),
-                        Line::from(vec![
-                            Span::styled("Path      ", Style::default().fg(theme.muted)),
-                            Span::styled(path_string, Style::default().fg(theme.status_fg)),
-                        ]),
-                    ];
-
-                    if let Some(res_line) = resolution_line {
-                        lines.insert(2, res_line);
-                    }
-                    lines
-                }
-                Err(_) => {
-                    vec![Line::from(Span::styled(
-                        "Unable to read metadata",
-                        Style::default().fg(theme.muted),
-                    ))]
-                }
+            //---------- Resolution of img -----------
+            // read from the header once per file version
+            let resolution_line = if is_file && image::ImageFormat::from_path(&path).is_ok() {
+                app.dimensions_for(&path).map(|(w, h)| {
+                    Line::from(vec![
+                        Span::styled("Resolution ", Style::default().fg(theme.muted)),
+                        Span::raw(format!("{}x{}", w, h)),
+                    ])
+                })
+            } else {
+                None
+            };
+
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled("Name      ", Style::default().fg(theme.muted)),
+                    Span::styled(file_name, Style::default().fg(theme.foreground)),
+                ]),
+                Line::from(vec![
+                    Span::styled("Type      ", Style::default().fg(theme.muted)),
+                    Span::raw(file_type),
+                ]),
+                Line::from(vec![
+                    Span::styled("Size      ", Style::default().fg(theme.muted)),
+                    Span::raw(size),
+                ]),
+                Line::from(vec![
+                    Span::styled("Perms     ", Style::default().fg(theme.muted)),
+                    Span::raw(format!("{} ({})", perms, octal)),
+                ]),
+                Line::from(vec![
+                    Span::styled("Owner     ", Style::default().fg(theme.muted)),
+                    Span::raw(owner),
+                ]),
+                Line::from(vec![
+                    Span::styled("Modified  ", Style::default().fg(theme.muted)),
+                    Span::raw(modified),
+                ]),
+                Line::from(""),
+                Line::from(vec![
+                    Span::styled("Path      ", Style::default().fg(theme.muted)),
+                    Span::styled(path_string, Style::default().fg(theme.status_fg)),
+                ]),
+            ];
+
+            if let Some(res_line) = resolution_line {
+                lines.insert(2, res_line);
             }
+
+            if let Some(link_line) = link_line {
+                lines.insert(2, link_line);
+            }
+
+            // PDF document info goes above the path
+            if is_file
+                && pdf::is_pdf(&path)
+                && let Some(Some(info)) = app.pdf_info_for(&path)
+            {
+                let at = lines.len() - 2;
+                lines.splice(at..at, pdf_metadata_lines(&info, theme));
+            }
+
+            if is_file
+                && exif::is_supported(&path)
+                && let Some(exif) = app.exif_for(&path)
+            {
+                let at = lines.len() - 2;
+                lines.splice(at..at, exif_metadata_lines(&exif, app.exif_expanded, config, theme));
+            }
+
+            if is_file
+                && media::kind(&path).is_some()
+            {
+                let media_lines = match app.media_info_for(&path) {
+                    Some(Some(info)) => media_metadata_lines(&info, theme),
+                    Some(None) => Vec::new(),
+                    None => vec![Line::from(Span::styled("Probing…", Style::default().fg(theme.muted)))],
+                };
+                let at = lines.len() - 2;
+                lines.splice(at..at, media_lines);
+            }
+
+            if is_file
+                && !pdf::is_pdf(&path)
+                && !exif::is_supported(&path)
+                && media::kind(&path).is_none()
+                && let Some(info) = app.text_info_for(&path)
+            {
+                let at = lines.len() - 2;
+                lines.splice(at..at, text_metadata_lines(&info, theme));
+            }
+            lines
         } else {
             vec![Line::from(Span::styled(
                 "No file selected",
@@ -418,12 +499,61 @@ pub fn draw_ui(
         // PREVIEW PANEL
         //
 
+        let hovered = match &app.archive {
+            Some(view) => view.entries.get(app.selected).map(|e| view.archive.join(&e.path)),
+            None => app.entries.get(app.selected).map(|e| e.path.clone()),
+        };
+
+        if let Some(path) = &hovered {
+            app.sync_preview_scroll(path);
+        }
+
+        // the page counter needs the page count before the block is drawn
+        let mut preview_title = " Preview ".to_string();
+
+        if app.archive.is_none()
+            && app.entries.get(app.selected).is_some_and(|e| e.is_file)
+            && let Some(path) = hovered.as_ref().filter(|p| pdf::is_pdf(p))
+        {
+            match app.pdf_info_for(path).map(|info| info.and_then(|info| info.pages)) {
+                Some(Some(pages)) => {
+                    app.preview_scroll = app.preview_scroll.min(pages.saturating_sub(1));
+                    preview_title = format!(" Preview — page {}/{} ", app.preview_scroll + 1, pages);
+                }
+                Some(None) => {
+                    // without pdfinfo, feel for the end one page at a time
+                    app.preview_scroll = app.preview_scroll.min(app.image_page + 1);
+                    preview_title = format!(" Preview — page {} ", app.preview_scroll + 1);
+                }
+                // page count still on its way
+                None => preview_title = format!(" Preview — page {} ", app.preview_scroll + 1),
+            }
+        }
+
+        // trees show how far they're unfolded
+        if !app.preview_raw
+            && app.archive.is_none()
+            && hovered
+                .as_deref()
+                .and_then(data::format)
+                .is_some_and(|f| !matches!(f, Format::Csv(_)))
+        {
+            preview_title = format!(" Preview — depth {} ", app.tree_depth);
+        }
+
+        if app.preview_raw && app.archive.is_none() && hovered.as_deref().is_some_and(has_raw_mode) {
+            preview_title = " Preview — source ".to_string();
+        }
+
         let preview_block = Block::default()
-            .title(" Preview ")
+            .title(preview_title)
             .borders(Borders::ALL);
 
         f.render_widget(preview_block.clone(), columns[2]);
         let inner = preview_block.inner(columns[2]);
+        app.preview_rect = columns[2];
+
+        let mut scroll_info: Option<ScrollInfo> = None;
 
         //
         // debounce guard
@@ -451,14 +581,76 @@ pub fn draw_ui(
         if let Some(rx) = &app.image_rx {
             while let Ok((id, result)) = rx.try_recv() {
                 if id == app.image_request_id {
+                    // a missing page means we scrolled past the end of the PDF
+                    if result.is_none() && app.image_page > 0 {
+                        app.preview_scroll = app.image_page - 1;
+                    }
+
                     app.image = result;
                     app.image_loading = false;
                 }
             }
         }
 
-        if let Some(entry) = app.entries.get(app.selected) {
-            let path: PathBuf = entry.path();
+        while let Ok((id, animation)) = app.animation_rx.try_recv() {
+            if id == app.image_request_id {
+                app.animation = Some(animation);
+                app.image_loading = false;
+            }
+        }
+
+        // nothing inside an archive animates
+        if app.archive.is_some() {
+            app.animation = None;
+        }
+
+        if let Some(view) = &app.archive {
+            //
+            // 📦 INSIDE AN ARCHIVE
+            //
+            let lines = match view.entries.get(app.selected) {
+                Some(entry) if entry.is_dir => {
+                    let children = archive::children(&view.members, &entry.path, app.show_hidden);
+                    let info = ScrollInfo::clamp(&mut app.preview_scroll, children.len(), inner.height as usize);
+                    scroll_info = Some(info);
+
+                    if children.is_empty() {
+                        vec![Line::from("(empty directory)")]
+                    } else {
+                        children
+                            .iter()
+                            .skip(info.offset)
+                            .take(inner.height as usize)
+                            .map(|e| {
+                                let icon = App::icon_for_kind(std::path::Path::new(&e.path), e.is_dir, app.icon_mode);
+                                Line::from(format!("{}{}", icon, e.name()))
+                            })
+                            .collect()
+                    }
+                }
+                Some(entry) => vec![
+                    Line::from(Span::styled(
+                        entry.name().to_string(),
+                        Style::default().fg(theme.foreground).add_modifier(Modifier::BOLD),
+                    )),
+                    Line::from(""),
+                    Line::from(Span::styled(
+                        format!("Archive member, {}", format_size(entry.size)),
+                        Style::default().fg(theme.muted),
+                    )),
+                    Line::from(Span::styled(
+                        "Copy and paste it to extract",
+                        Style::default().fg(theme.muted),
+                    )),
+                ],
+                None => Vec::new(),
+            };
+
+            f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+        } else if let Some(entry) = app.entries.get(app.selected) {
+            let path: PathBuf = entry.path.clone();
+            // as of the listing, which the watcher keeps current
+            let (is_file, is_dir, stamp) = (entry.is_file, entry.is_dir, entry.stamp());
 
             let ext = path
                 .extension()
@@ -472,28 +664,103 @@ pub fn draw_ui(
             );
 
             let is_pdf = ext == "pdf";
+            let is_svg = ext == "svg" && !app.preview_raw;
+
+            let media_kind = media::kind(&path);
+
+            // a configured previewer takes over from the built-in ones
+            let external = match previewer::find(&config.previewers, &path).filter(|_| is_file) {
+                Some(previewer) => {
+                    let key = PreviewerKey {
+                        path: path.clone(),
+                        stamp,
+                        width: inner.width,
+                        height: inner.height,
+                    };
+
+                    Some(app.external_previews.request(&key, &previewer.command))
+                }
+                None => {
+                    app.external_previews.cancel();
+                    None
+                }
+            };
+
+            let image_kind = match &external {
+                Some(Some(Output::Image(rendered))) => Some(PreviewKind::Rendered(rendered.clone())),
+                Some(_) => None,
+                None => match media_kind {
+                    Some(kind) => Some(PreviewKind::Media(kind)),
+                    None if is_pdf => Some(PreviewKind::Pdf),
+                    None if is_svg => Some(PreviewKind::Svg),
+                    None if is_image => Some(PreviewKind::Image),
+                    None => None,
+                },
+            };
+
+            //
+            // 🔌 EXTERNAL PREVIEWER TEXT
+            //
+            if let Some(output) = external.filter(|o| !matches!(o, Some(Output::Image(_)))) {
+                app.image = None;
+                app.animation = None;
+                app.image_path = None;
+                app.image_loading = false;
+                app.image_size = None;
+
+                let lines = match output {
+                    Some(Output::Text(lines)) => {
+                        let info = ScrollInfo::clamp(&mut app.preview_scroll, lines.len(), inner.height as usize);
+                        scroll_info = Some(info);
+
+                        lines.into_iter().skip(info.offset).take(inner.height as usize).collect()
+                    }
+                    Some(Output::Failed(message)) => vec![Line::from(Span::styled(
+                        message,
+                        Style::default().fg(theme.muted),
+                    ))],
+                    _ => vec![Line::from(Span::styled(
+                        "Running previewer…",
+                        Style::default().fg(theme.muted),
+                    ))],
+                };
+
+                f.render_widget(Paragraph::new(lines), inner);
 
             //
-            // 🖼 IMAGE / PDF PREVIEW
+            // 🖼 IMAGE / PDF / MEDIA PREVIEW
             //
-            if (is_image || is_pdf) && path.is_file() {
+            } else if let Some(kind) = image_kind.filter(|_| is_file) {
+
+                // PDFs scroll a page at a time, images don't scroll
+                if !is_pdf {
+                    app.preview_scroll = 0;
+                }
+                let page = app.preview_scroll;
 
                 let key = ImageKey {
                     path: path.clone(),
+                    stamp,
                     width: quantize(inner.width),
                     height: quantize(inner.height),
+                    page,
                 };
 
-                if let Some(cached) = app.image_cache.lock().unwrap().get(&key).cloned() {
+                if let Some(cached) = app.image_cache.lock().unwrap().get(&key) {
                     app.image = Some(cached);
+                    app.animation = None;
                     app.image_loading = false;
                     app.image_path = Some(path.clone());
+                    app.image_stamp = Some(key.stamp);
                     app.image_size = Some((inner.width, inner.height));
+                    app.image_page = page;
                 }
 
                 let size_changed = app.image_size != Some((inner.width, inner.height));
-                let path_changed = app.image_path.as_ref() != Some(&path);
-                let reload = size_changed || path_changed;
+                // an edited file counts as a different one
+                let path_changed = app.image_path.as_ref() != Some(&path) || app.image_stamp != Some(key.stamp);
+                let page_changed = app.image_page != page;
+                let reload = size_changed || path_changed || page_changed;
 
                 if reload && !app.image_loading {
 
@@ -510,6 +777,7 @@ pub fn draw_ui(
                         .store(request_id, Ordering::Relaxed);
 
                     app.image = None;
+                    app.animation = None;
                     app.preview_deadline = Some(
                         std::time::Instant::now()
                             + std::time::Duration::from_millis(60)
@@ -517,20 +785,53 @@ pub fn draw_ui(
 
                     app.image_size = Some((inner.width, inner.height));
                     app.image_path = Some(path.clone());
+                    app.image_stamp = Some(key.stamp);
+                    app.image_page = page;
                     app.image_loading = true;
 
                     app.preview_job_tx.send(PreviewJob {
                         request_id,
+                        key: key.clone(),
                         path: path.clone(),
                         inner,
-                        is_pdf,
+                        kind,
+                        page,
                     }).ok();
                 }
 
                 // render image
-                if let Some(img) = &app.image {
+                if let Some(animation) = &mut app.animation {
+                    f.render_widget(Image::new(animation.frame()), inner);
+                } else if let Some(img) = &app.image {
                     let widget = Image::new(img);
                     f.render_widget(widget, inner);
+                } else if is_svg && !app.image_loading {
+                    let message = format!(
+                        "Install rsvg-convert or resvg to render SVG\n\n{} shows the source",
+                        config.keymaps.preview_raw,
+                    );
+
+                    f.render_widget(
+                        Paragraph::new(message)
+                            .style(Style::default().fg(theme.muted))
+                            .alignment(Alignment::Center),
+                        inner,
+                    );
+                } else if media_kind.is_some() && !app.image_loading {
+                    // no thumbnailer and no cover art: describe the file instead
+                    let lines = match app.media_info_for(&path) {
+                        Some(Some(info)) => media_metadata_lines(&info, theme),
+                        Some(None) => vec![Line::from(Span::styled(
+                            "No preview (install ffmpeg for thumbnails)",
+                            Style::default().fg(theme.muted),
+                        ))],
+                        None => vec![Line::from(Span::styled(
+                            "Reading media info…",
+                            Style::default().fg(theme.muted),
+                        ))],
+                    };
+
+                    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
                 } else {
                     let loading = Paragraph::new("Loading preview…")
                         .alignment(Alignment::Center);
@@ -541,6 +842,7 @@ pub fn draw_ui(
                 // 📄 TEXT PREVIEW
                 //
                 app.image = None;
+                app.animation = None;
                 app.image_path = None;
                 app.image_loading = false;
                 app.image_size = None;
@@ -550,61 +852,46 @@ pub fn draw_ui(
                     "png" | "jpg" | "jpeg" | "webp" | "gif"
                         | "mp3" | "wav" | "flac"
                         | "mp4" | "mkv" | "mov"
-                        | "zip" | "tar" | "gz" | "rar"
+                        | "zip" | "tar" | "gz" | "tgz" | "xz" | "zst" | "rar"
                         | "exe" | "bin" | "so" | "pdf"
                 );
 
-                let is_probably_text = !is_binary_ext;
+                // sniffing also spots UTF-16, whose NULs would otherwise look binary
+                let text_info = is_file.then(|| app.text_info_for(&path)).flatten();
+                let is_binary = is_binary_ext || (is_file && text_info.is_none());
+
+                let is_probably_text = !is_binary;
 
                 //
                 // 📁 DIRECTORY / TEXT / FALLBACK PREVIEW (FIXED)
                 //
 
-                if path.is_dir() {
-                    use std::fs;
-
+                if is_dir {
+                    // listed in the background, once per change of the directory
                     let mut lines = Vec::new();
+                    let icon_mode = app.icon_mode;
+                    let show_hidden = app.show_hidden;
+
+                    match app.dir_preview.request(&path, stamp, show_hidden) {
+                        Some(Ok(items)) => {
+                            let info = ScrollInfo::clamp(&mut app.preview_scroll, items.len(), inner.height as usize);
+                            scroll_info = Some(info);
 
-                    match fs::read_dir(&path) {
-                        Ok(read_dir) => {
-                            let mut items: Vec<_> = read_dir
-                                .flatten()
-                                .filter(|e| {
-                                    if let Some(name) = e.file_name().to_str() {
-                                        if !app.show_hidden && name.starts_with('.') {
-                                            return false;
-                                        }
-                                    }
-                                    true
-                                })
-                                .collect();
-
-                            items.sort_by(|a, b| {
-                                use std::cmp::Ordering;
-
-                                let a_dir = a.file_type().map(|t| t.is_dir()).unwrap_or(false);
-                                let b_dir = b.file_type().map(|t| t.is_dir()).unwrap_or(false);
-
-                                if a_dir != b_dir {
-                                    return if a_dir { Ordering::Less } else { Ordering::Greater };
-                                }
-
-                                a.file_name().cmp(&b.file_name())
-                            });
-
-                            for entry in items.into_iter().take(inner.height as usize) {
-                                let name = entry.file_name().to_string_lossy().to_string();
-                                let icon = App::icon_for(&entry.path(), app.icon_mode);
-                                lines.push(format!("{}{}", icon, name));
+                            for entry in items.iter().skip(info.offset).take(inner.height as usize) {
+                                lines.push(format!("{}{}", App::icon(entry.kind, icon_mode), entry.name));
                             }
 
                             if lines.is_empty() {
                                 lines.push("(empty directory)".into());
                             }
                         }
-                        Err(_) => {
+                        Some(Err(_)) => {
                             lines.push("Unable to read directory".into());
                         }
+                        None => match app.dir_preview.progress() {
+                            Some(count) => lines.push(format!("Loading {} entries…", count)),
+                            None => lines.push("Loading preview…".into()),
+                        },
                     }
 
                     let preview = Paragraph::new(lines.join("\n"))
@@ -612,21 +899,152 @@ pub fn draw_ui(
 
                     f.render_widget(preview, inner);
                 }
-                else if is_probably_text && path.is_file() {
-                    let content = std::fs::read_to_string(&path)
-                        .map(|s| {
-                            s.lines()
-                                .take(inner.height as usize)
+                else if is_file && ArchiveKind::detect(&path).is_some() {
+                    //
+                    // 📦 ARCHIVE LISTING
+                    //
+                    // listed in the background, decompressing can take a while
+                    let lines = match app.archive_preview.request(&path, list_archive) {
+                        Some(Ok(members)) => {
+                            let visible = (inner.height as usize).saturating_sub(2);
+                            let info = ScrollInfo::clamp(&mut app.preview_scroll, members.len(), visible);
+                            scroll_info = Some(info);
+
+                            archive_listing_lines(members, info.offset, visible, theme)
+                        }
+                        Some(Err(err)) => vec![Line::from(Span::styled(
+                            format!("Unable to read archive: {}", err),
+                            Style::default().fg(theme.muted),
+                        ))],
+                        None => vec![Line::from("Loading preview…")],
+                    };
+
+                    f.render_widget(Paragraph::new(lines), inner);
+                }
+                else if is_probably_text
+                    && let Some(info) = text_info
+                    && !app.preview_raw
+                    && let Some(format) = data::format(&path)
+                    && stamp.size <= data::MAX_SIZE
+                {
+                    //
+                    // 🗂 JSON / YAML / TOML / CSV PREVIEW
+                    //
+                    let key = DataKey {
+                        path: path.clone(),
+                        stamp,
+                        depth: if matches!(format, Format::Csv(_)) { 0 } else { app.tree_depth },
+                        width: inner.width,
+                    };
+
+                    let lines = match app.data_previews.request(&key, format, info.encoding, theme) {
+                        Some(Ok(lines)) => {
+                            let info = ScrollInfo::clamp(&mut app.preview_scroll, lines.len(), inner.height as usize);
+                            scroll_info = Some(info);
+
+                            lines.iter().skip(info.offset).take(inner.height as usize).cloned().collect()
+                        }
+                        Some(Err(_)) => vec![Line::from("Unable to read file")],
+                        None => vec![Line::from("Loading preview…")],
+                    };
+
+                    f.render_widget(Paragraph::new(lines), inner);
+                }
+                else if is_probably_text
+                    && let Some(info) = text_info
+                    && markdown::is_markdown(&path)
+                    && !app.preview_raw
+                    && stamp.size <= markdown::MAX_SIZE
+                {
+                    //
+                    // 📝 MARKDOWN PREVIEW
+                    //
+                    let key = MarkdownKey {
+                        path: path.clone(),
+                        stamp,
+                        width: inner.width,
+                    };
+
+                    let lines = match app.markdown_previews.request(&key, info.encoding, theme) {
+                        Some(Ok(lines)) => {
+                            let info = ScrollInfo::clamp(&mut app.preview_scroll, lines.len(), inner.height as usize);
+                            scroll_info = Some(info);
+
+                            lines.iter().skip(info.offset).take(inner.height as usize).cloned().collect()
+                        }
+                        Some(Err(_)) => vec![Line::from("Unable to read file")],
+                        None => vec![Line::from("Loading preview…")],
+                    };
+
+                    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+                }
+                else if is_probably_text && let Some(text_info) = text_info {
+                    let visible = inner.height as usize;
+
+                    let content = match app.text_preview_for(&path, text_info.encoding, visible) {
+                        Some(Ok(window)) => {
+                            // any line can go on top until the next window takes over,
+                            // as long lines may leave it less than a screen to show
+                            let scrollable = match window.next {
+                                Some(_) => window.lines.len() + visible.saturating_sub(1),
+                                None => window.lines.len(),
+                            };
+                            let mut info = ScrollInfo::clamp(&mut app.preview_scroll, scrollable, visible);
+
+                            // only part of the file is loaded, so place the view by bytes
+                            let size = stamp.size;
+                            if window.len < size && !window.lines.is_empty() {
+                                let bytes_per_line = window.len as f64 / window.lines.len() as f64;
+                                let before = (window.start as f64 / bytes_per_line) as usize;
+
+                                // the last window knows exactly how many lines are left
+                                info.total = match window.next {
+                                    Some(_) => ((size as f64 / bytes_per_line) as usize).max(before + window.lines.len()),
+                                    None => before + window.lines.len(),
+                                };
+                                info.offset += before;
+                                info.approximate = true;
+                            }
+                            scroll_info = Some(info);
+
+                            window.lines
+                                .iter()
+                                .skip(app.preview_scroll)
+                                .take(visible)
+                                .map(String::as_str)
                                 .collect::<Vec<_>>()
                                 .join("\n")
-                        })
-                        .unwrap_or_else(|_| "Unable to read file".to_string());
+                        }
+                        Some(Err(_)) => "Unable to read file".to_string(),
+                        None => "Loading preview…".to_string(),
+                    };
 
                     let preview = Paragraph::new(content)
                         .wrap(Wrap { trim: false });
 
                     f.render_widget(preview, inner);
                 }
+                else if is_file {
+                    //
+                    // 🔢 HEX DUMP PREVIEW
+                    //
+                    let per_row = crate::hexdump::bytes_per_row(inner.width);
+                    let file_len = stamp.size;
+                    let total_rows = file_len.div_ceil(per_row as u64) as usize;
+
+                    let info = ScrollInfo::clamp(&mut app.preview_scroll, total_rows, inner.height as usize);
+                    scroll_info = Some(info);
+
+                    let offset = (info.offset * per_row) as u64;
+                    let len = inner.height as usize * per_row;
+
+                    let lines = match crate::hexdump::read_window(&path, offset, len) {
+                        Ok(bytes) => crate::hexdump::render(&bytes, offset, per_row, theme),
+                        Err(_) => vec![Line::from("Unable to read file")],
+                    };
+
+                    f.render_widget(Paragraph::new(lines), inner);
+                }
                 else {
                     let preview = Paragraph::new("No preview available")
                         .alignment(Alignment::Center)
@@ -637,6 +1055,10 @@ pub fn draw_ui(
             }
         }
 
+        if let Some(info) = scroll_info {
+            render_scroll_indicator(f, columns[2], info, theme);
+        }
+
         //
         // STATUS BAR
         //
@@ -648,7 +1070,7 @@ pub fn draw_ui(
             ])
             .split(vertical[2]);
 
-        let left_status = Paragraph::new(Line::from(vec![
+        let mut status_spans = vec![
             Span::styled(
                 "[Fren] ",
                 Style::default()
@@ -658,7 +1080,32 @@ pub fn draw_ui(
             Span::raw(format!(
                 " /: help ",
             )),
-        ]))
+        ];
+
+        if !app.marked.is_empty() {
+            status_spans.push(Span::styled(
+                format!(" {} marked ", app.marked.len()),
+                Style::default().fg(theme.focus_border),
+            ));
+        }
+
+        if let Some(job) = app.jobs.active.first() {
+            status_spans.push(Span::raw(format!(
+                " {} {}% {} ",
+                job.label,
+                job.percent(),
+                job.current,
+            )));
+        } else if let Some(notice) = app.notices.toast() {
+            let color = match notice.level {
+                Level::Info => theme.status_fg,
+                Level::Warning => theme.warning,
+                Level::Error => theme.error,
+            };
+            status_spans.push(Span::styled(format!(" {} ", notice.message), Style::default().fg(color)));
+        }
+
+        let left_status = Paragraph::new(Line::from(status_spans))
         .style(Style::default().bg(theme.status_bg).fg(theme.status_fg));
 
         f.render_widget(left_status, status_chunks[0]);
@@ -692,9 +1139,20 @@ pub fn draw_ui(
                 InputAction::CreateFolder => " Create Folder ",
                 InputAction::ConfirmDelete => " Confirm Delete ",
                 InputAction::OpenWith => " Open With ",
+                InputAction::Extract => " Extract To Folder (empty: here) ",
+                InputAction::Compress => " Compress As (.zip .tar .tar.gz .tar.xz .tar.zst) ",
+                InputAction::ResolveConflict => " Already Exists ",
+            };
+
+            let body = match (action, &app.pending_task) {
+                (InputAction::ResolveConflict, Some((_, names))) => format!(
+                    "{}\n\n(o)verwrite  (r)ename  (s)kip  (n) cancel",
+                    names.join(", ")
+                ),
+                _ => app.input.clone(),
             };
 
-            let input = Paragraph::new(app.input.as_str())
+            let input = Paragraph::new(body)
                 .style(Style::default().fg(theme.foreground).bg(theme.background))
                 .block(
                     Block::default()
@@ -716,12 +1174,303 @@ pub fn draw_ui(
             draw_help_popup(f, area, config, theme);
         }
 
+        if app.show_log {
+            draw_log_popup(f, area, &app.notices, theme);
+        }
+
     })?;
 
     Ok(())
 }
 
 
+// files with both a rendered and a source view
+fn has_raw_mode(path: &std::path::Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    matches!(
+        ext.as_str(),
+        "svg" | "md" | "markdown" | "json" | "yaml" | "yml" | "toml" | "csv" | "tsv"
+    )
+}
+
+//
+// Preview scroll position
+//
+#[derive(Clone, Copy)]
+struct ScrollInfo {
+    offset: usize,
+    total: usize,
+    visible: usize,
+    // estimated from a partial read, so shown as a percentage
+    approximate: bool,
+}
+
+impl ScrollInfo {
+    // keep the stored scroll inside the content
+    fn clamp(scroll: &mut usize, total: usize, visible: usize) -> Self {
+        *scroll = (*scroll).min(total.saturating_sub(visible));
+
+        Self {
+            offset: *scroll,
+            total,
+            visible,
+            approximate: false,
+        }
+    }
+}
+
+fn render_scroll_indicator(f: &mut ratatui::Frame, area: Rect, info: ScrollInfo, theme: &Theme) {
+    if info.total <= info.visible {
+        return;
+    }
+
+    let mut state = ScrollbarState::new(info.total - info.visible).position(info.offset);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .thumb_style(Style::default().fg(theme.focus_border))
+        .track_style(Style::default().fg(theme.border));
+
+    f.render_stateful_widget(scrollbar, area.inner(Margin { vertical: 1, horizontal: 0 }), &mut state);
+
+    let last = (info.offset + info.visible).min(info.total);
+    let label = if info.approximate {
+        format!(" ~{}% ", last * 100 / info.total)
+    } else {
+        format!(" {}-{}/{} ", info.offset + 1, last, info.total)
+    };
+    render_border_label(f, area, label, theme);
+}
+
+// right-aligned on the bottom border
+fn render_border_label(f: &mut ratatui::Frame, area: Rect, label: String, theme: &Theme) {
+    let width = label.width() as u16;
+
+    if area.width < width + 2 || area.height < 2 {
+        return;
+    }
+
+    let rect = Rect {
+        x: area.x + area.width - width - 1,
+        y: area.y + area.height - 1,
+        width,
+        height: 1,
+    };
+
+    f.render_widget(Paragraph::new(label).style(Style::default().fg(theme.muted)), rect);
+}
+
+//
+// PDF document info
+//
+fn pdf_metadata_lines(info: &PdfInfo, theme: &Theme) -> Vec<Line<'static>> {
+    let fields = [
+        ("Pages     ", info.pages.map(|n| n.to_string())),
+        ("Title     ", info.title.clone()),
+        ("Author    ", info.author.clone()),
+        ("Producer  ", info.producer.clone()),
+    ];
+
+    fields
+        .into_iter()
+        .filter_map(|(label, value)| {
+            Some(Line::from(vec![
+                Span::styled(label, Style::default().fg(theme.muted)),
+                Span::raw(value?),
+            ]))
+        })
+        .collect()
+}
+
+//
+// EXIF: a one-line summary that expands into the full section
+//
+fn exif_metadata_lines(exif: &Exif, expanded: bool, config: &Config, theme: &Theme) -> Vec<Line<'static>> {
+    let header = |marker: &str, summary: Option<String>| {
+        let mut spans = vec![Span::styled(format!("{} EXIF    ", marker), Style::default().fg(theme.muted))];
+        if let Some(summary) = summary {
+            spans.push(Span::raw(format!("{}  ", summary)));
+        }
+        spans.push(Span::styled(
+            format!("({})", config.keymaps.toggle_exif),
+            Style::default().fg(theme.muted),
+        ));
+        Line::from(spans)
+    };
+
+    if !expanded {
+        return vec![header("▸", exif.camera().or_else(|| exif.settings()))];
+    }
+
+    let gps = exif.gps.map(|(lat, lon)| match exif.altitude {
+        Some(alt) => format!("{:.5}, {:.5}  {:.0}m", lat, lon, alt),
+        None => format!("{:.5}, {:.5}", lat, lon),
+    });
+
+    let fields = [
+        ("Camera    ", exif.camera()),
+        ("Lens      ", exif.lens.clone()),
+        ("Settings  ", exif.settings()),
+        ("Taken     ", exif.taken.clone()),
+        ("GPS       ", gps),
+        ("Orient    ", exif.orientation_name().map(str::to_string)),
+    ];
+
+    let mut lines = vec![header("▾", None)];
+    lines.extend(fields.into_iter().filter_map(|(label, value)| {
+        Some(Line::from(vec![
+            Span::styled(label, Style::default().fg(theme.muted)),
+            Span::raw(value?),
+        ]))
+    }));
+
+    lines
+}
+
+//
+// Text encoding and line endings
+//
+fn text_metadata_lines(info: &TextInfo, theme: &Theme) -> Vec<Line<'static>> {
+    vec![
+        Line::from(vec![
+            Span::styled("Encoding  ", Style::default().fg(theme.muted)),
+            Span::raw(info.encoding.name()),
+        ]),
+        Line::from(vec![
+            Span::styled("Newlines  ", Style::default().fg(theme.muted)),
+            Span::raw(info.line_ending.name()),
+        ]),
+    ]
+}
+
+//
+// Media stream info and tags
+//
+fn media_metadata_lines(info: &MediaInfo, theme: &Theme) -> Vec<Line<'static>> {
+    let mut fields = vec![
+        ("Duration  ".to_string(), info.duration.map(media::format_duration)),
+        ("Codec     ".to_string(), info.codec.clone()),
+        ("Video     ".to_string(), info.resolution.map(|(w, h)| format!("{}x{}", w, h))),
+        ("Bitrate   ".to_string(), info.bitrate.map(|b| format!("{} kb/s", b / 1000))),
+    ];
+
+    for (tag, value) in &info.tags {
+        let mut label = tag.clone();
+        label[..1].make_ascii_uppercase();
+        fields.push((format!("{:<10}", label), Some(value.clone())));
+    }
+
+    fields
+        .into_iter()
+        .filter_map(|(label, value)| {
+            Some(Line::from(vec![
+                Span::styled(label, Style::default().fg(theme.muted)),
+                Span::raw(value?),
+            ]))
+        })
+        .collect()
+}
+
+//
+// Archive listing: summary line, then size / date / path per member
+//
+fn archive_listing_lines(
+    members: &[ArchiveEntry],
+    scroll: usize,
+    visible: usize,
+    theme: &Theme,
+) -> Vec<Line<'static>> {
+    let files = members.iter().filter(|m| !m.is_dir).count();
+    let total: u64 = members.iter().map(|m| m.size).sum();
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} files, {} uncompressed", files, format_size(total)),
+            Style::default().fg(theme.muted),
+        )),
+        Line::from(""),
+    ];
+
+    for member in members.iter().skip(scroll).take(visible) {
+        let size = if member.is_dir {
+            "-".to_string()
+        } else {
+            format_size(member.size)
+        };
+
+        let date = member
+            .modified
+            .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".repeat(16));
+
+        let (name, color) = if member.is_dir {
+            (format!("{}/", member.path), theme.directory)
+        } else {
+            (member.path.clone(), theme.foreground)
+        };
+
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>10}  ", size), Style::default().fg(theme.muted)),
+            Span::styled(format!("{}  ", date), Style::default().fg(theme.muted)),
+            Span::styled(name, Style::default().fg(color)),
+        ]));
+    }
+
+    if members.is_empty() {
+        lines.push(Line::from("(empty archive)"));
+    }
+
+    lines
+}
+
+fn archive_metadata_lines(archive: &std::path::Path, entry: &ArchiveEntry, theme: &Theme) -> Vec<Line<'static>> {
+    let modified = entry
+        .modified
+        .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Name      ", Style::default().fg(theme.muted)),
+            Span::styled(entry.name().to_string(), Style::default().fg(theme.foreground)),
+        ]),
+        Line::from(vec![
+            Span::styled("Type      ", Style::default().fg(theme.muted)),
+            Span::raw(if entry.is_dir { "Archive directory" } else { "Archive member" }),
+        ]),
+        Line::from(vec![
+            Span::styled("Size      ", Style::default().fg(theme.muted)),
+            Span::raw(format_size(entry.size)),
+        ]),
+        Line::from(vec![
+            Span::styled("Modified  ", Style::default().fg(theme.muted)),
+            Span::raw(modified),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Path      ", Style::default().fg(theme.muted)),
+            Span::styled(
+                archive.join(&entry.path).display().to_string(),
+                Style::default().fg(theme.status_fg),
+            ),
+        ]),
+    ];
+
+    if let Some(mode) = entry.mode {
+        lines.insert(3, Line::from(vec![
+            Span::styled("Perms     ", Style::default().fg(theme.muted)),
+            Span::raw(format!("{} ({:o})", format_permissions(mode), mode & 0o777)),
+        ]));
+    }
+
+    lines
+}
+
 //
 // Dim overlay
 //
@@ -766,8 +1515,22 @@ fn draw_help_popup(
         Line::from(format!("Pin                : {}", config.keymaps.pin)),
         Line::from(format!("Unpin              : {}", config.keymaps.unpin)),
         Line::from(format!("Sorting mode       : {}", config.keymaps.sort)),
+        Line::from(format!("Scroll preview     : {} / {}", config.keymaps.preview_down, config.keymaps.preview_up)),
+        Line::from(format!("Half page          : {} / {}", config.keymaps.preview_half_down, config.keymaps.preview_half_up)),
+        Line::from(format!("Preview top/bottom : {} / {}", config.keymaps.preview_top, config.keymaps.preview_bottom)),
+        Line::from(format!("PDF page next/prev : {} / {}", config.keymaps.next_page, config.keymaps.prev_page)),
+        Line::from(format!("Rendered / source  : {}", config.keymaps.preview_raw)),
+        Line::from(format!("Expand EXIF        : {}", config.keymaps.toggle_exif)),
+        Line::from(format!("Tree depth +/-     : {} / {}", config.keymaps.tree_expand, config.keymaps.tree_collapse)),
+        Line::from(format!("Mark               : {}", config.keymaps.mark)),
+        Line::from(format!("Extract archive    : {}", config.keymaps.extract)),
+        Line::from(format!("Compress selection : {}", config.keymaps.compress)),
+        Line::from(format!("Message log        : {}", config.keymaps.messages)),
+        Line::from("Cancel job/marks   : Esc"),
         Line::from(format!("Focus switch       : {}", config.keymaps.focus)),
         Line::from(format!("Quit               : {}", config.keymaps.quit)),
+        Line::from(format!("Quit and cd        : {}", config.keymaps.quit_cd)),
+        Line::from(format!("Choose (picker)    : Enter / {}", config.keymaps.choose)),
         Line::from(""),
         Line::from(Span::styled(
             "Press ESC to close",
@@ -815,6 +1578,57 @@ fn draw_help_popup(
     f.render_widget(paragraph, popup_area);
 }
 
+//
+// Message log
+//
+fn draw_log_popup(f: &mut ratatui::Frame, area: Rect, notices: &Notifications, theme: &Theme) {
+    render_dim_overlay(f, area, theme);
+
+    let popup_area = centered_rect(70, 60, area);
+    let visible = popup_area.height.saturating_sub(2) as usize;
+
+    // newest at the bottom, oldest scrolled off the top
+    let mut lines: Vec<Line> = notices
+        .log
+        .iter()
+        .skip(notices.log.len().saturating_sub(visible))
+        .map(|notice| {
+            let color = match notice.level {
+                Level::Info => theme.foreground,
+                Level::Warning => theme.warning,
+                Level::Error => theme.error,
+            };
+
+            Line::from(vec![
+                Span::styled(notice.time.format("%H:%M:%S ").to_string(), Style::default().fg(theme.muted)),
+                Span::styled(format!("{:<6}", notice.level.name()), Style::default().fg(color)),
+                Span::raw(notice.message.clone()),
+            ])
+        })
+        .collect();
+
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled("No messages", Style::default().fg(theme.muted))));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(theme.foreground).bg(theme.background))
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    " Messages ",
+                    Style::default()
+                        .fg(theme.focus_border)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border)),
+        );
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(paragraph, popup_area);
+}
+
 //
 // Centered rect
 //
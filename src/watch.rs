@@ -0,0 +1,186 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CString, OsStr},
+    io,
+    mem,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    ptr,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::event::AppEvent;
+
+// a burst of events is reported once it has been quiet this long...
+const QUIET: Duration = Duration::from_millis(100);
+// ...or once it has gone on this long, so a busy directory still updates
+const MAX_DELAY: Duration = Duration::from_millis(500);
+
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF;
+
+//
+// inotify
+//
+/// Watches a few directories and reports which paths in them changed.
+pub struct Watcher {
+    // -1 when inotify isn't available, in which case nothing is watched
+    fd: i32,
+    dirs: Arc<Mutex<HashMap<i32, PathBuf>>>,
+    rx: Receiver<HashSet<PathBuf>>,
+}
+
+impl Watcher {
+    pub fn new(waker: Sender<AppEvent>) -> Self {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        let dirs = Arc::new(Mutex::new(HashMap::new()));
+        let (tx, rx) = mpsc::channel();
+
+        if fd >= 0 {
+            let dirs = dirs.clone();
+            thread::spawn(move || read_events(fd, &dirs, &tx, &waker));
+        }
+
+        Self { fd, dirs, rx }
+    }
+
+    /// Watch exactly these directories, dropping any others.
+    pub fn watch(&mut self, wanted: &[&Path]) {
+        if self.fd < 0 {
+            return;
+        }
+
+        let mut dirs = self.dirs.lock().unwrap();
+
+        dirs.retain(|&wd, dir| {
+            let keep = wanted.contains(&dir.as_path());
+            if !keep {
+                unsafe { libc::inotify_rm_watch(self.fd, wd) };
+            }
+            keep
+        });
+
+        for &dir in wanted {
+            if dirs.values().any(|d| d == dir) {
+                continue;
+            }
+
+            let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+                continue;
+            };
+
+            let wd = unsafe { libc::inotify_add_watch(self.fd, c_path.as_ptr(), MASK) };
+            if wd >= 0 {
+                dirs.insert(wd, dir.to_path_buf());
+            }
+        }
+    }
+
+    /// Paths that changed since the last call, a watched directory itself
+    /// standing for "anything in here".
+    pub fn poll(&mut self) -> HashSet<PathBuf> {
+        self.rx.try_iter().flatten().collect()
+    }
+}
+
+fn read_events(fd: i32, dirs: &Mutex<HashMap<i32, PathBuf>>, tx: &Sender<HashSet<PathBuf>>, waker: &Sender<AppEvent>) {
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        // block for the first event of a burst
+        if !wait(fd, None) {
+            return;
+        }
+
+        let started = Instant::now();
+        let mut changed = HashSet::new();
+
+        loop {
+            match read(fd, &mut buf) {
+                Ok(n) => collect(&buf[..n], &dirs.lock().unwrap(), &mut changed),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return,
+            }
+
+            let left = MAX_DELAY.saturating_sub(started.elapsed());
+            if left.is_zero() || !wait(fd, Some(QUIET.min(left))) {
+                break;
+            }
+        }
+
+        // e.g. only the IN_IGNORED of a watch we just removed
+        if changed.is_empty() {
+            continue;
+        }
+
+        if tx.send(changed).is_err() {
+            return;
+        }
+        let _ = waker.send(AppEvent::Worker);
+    }
+}
+
+// true once fd is readable, false on timeout or error
+fn wait(fd: i32, timeout: Option<Duration>) -> bool {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.map(|t| t.as_millis() as i32).unwrap_or(-1);
+
+    loop {
+        match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+            1.. => return pollfd.revents & libc::POLLIN != 0,
+            0 => return false,
+            _ if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+            _ => return false,
+        }
+    }
+}
+
+fn read(fd: i32, buf: &mut [u8]) -> io::Result<usize> {
+    match unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) } {
+        n if n < 0 => Err(io::Error::last_os_error()),
+        n => Ok(n as usize),
+    }
+}
+
+// each event is a header followed by a NUL padded name
+fn collect(mut buf: &[u8], dirs: &HashMap<i32, PathBuf>, changed: &mut HashSet<PathBuf>) {
+    const HEADER: usize = mem::size_of::<libc::inotify_event>();
+
+    while buf.len() >= HEADER {
+        let event: libc::inotify_event = unsafe { ptr::read_unaligned(buf.as_ptr().cast()) };
+        let end = (HEADER + event.len as usize).min(buf.len());
+        let name = &buf[HEADER..end];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        buf = &buf[end..];
+
+        // the queue overflowed, so anything may have changed
+        if event.mask & libc::IN_Q_OVERFLOW != 0 {
+            changed.extend(dirs.values().cloned());
+            continue;
+        }
+
+        if let Some(dir) = dirs.get(&event.wd) {
+            changed.insert(if name.is_empty() {
+                dir.clone()
+            } else {
+                dir.join(OsStr::from_bytes(name))
+            });
+        }
+    }
+}